@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::{
+    color::Color,
+    vec3::{Vec3, unit_vector},
+};
+
+/// Light arriving from directions in which a ray hits nothing.
+pub trait Environment: Send + Sync {
+    fn value(&self, direction: &Vec3) -> Color;
+
+    /// Picks a direction to gather light from, with its density over solid
+    /// angle, or `None` if the environment is not importance sampled.
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /// Density with which `sample` picks `direction`.
+    fn pdf(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// What the camera sees, and the scene is lit by, when a ray escapes.
+#[derive(Clone)]
+pub enum Background {
+    /// The same color in every direction; black for scenes lit only by
+    /// their own lights.
+    Solid(Color),
+    /// Blends from `bottom` straight down to `top` straight up.
+    Gradient {
+        bottom: Color,
+        top: Color,
+    },
+    Environment(Arc<dyn Environment>),
+}
+
+impl Default for Background {
+    /// The white-to-blue sky of the original renderer.
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Background {
+    pub fn value(&self, direction: &Vec3) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let a = 0.5 * (unit_vector(direction).y() + 1.0);
+                Color((1.0 - a) * bottom.0 + a * top.0)
+            }
+            Background::Environment(environment) => environment.value(direction),
+        }
+    }
+
+    /// See `Environment::sample`; plain colors are left to the materials to
+    /// find.
+    pub fn sample(&self) -> Option<(Vec3, f64)> {
+        match self {
+            Background::Environment(environment) => environment.sample(),
+            _ => None,
+        }
+    }
+
+    pub fn pdf(&self, direction: &Vec3) -> f64 {
+        match self {
+            Background::Environment(environment) => environment.pdf(direction),
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Brighter straight up, and sampled only straight up.
+    struct Zenith;
+
+    impl Environment for Zenith {
+        fn value(&self, direction: &Vec3) -> Color {
+            let up = unit_vector(direction).y().max(0.0);
+            Color::new(up, up, up)
+        }
+
+        fn sample(&self) -> Option<(Vec3, f64)> {
+            Some((Vec3::new(0.0, 1.0, 0.0), 2.0))
+        }
+
+        fn pdf(&self, direction: &Vec3) -> f64 {
+            if direction.y() > 0.0 { 2.0 } else { 0.0 }
+        }
+    }
+
+    fn assert_color(found: Color, expected: [f64; 3]) {
+        for (f, e) in found.0.e.into_iter().zip(expected) {
+            assert!((f - e).abs() < 1e-12, "{:?} vs {expected:?}", found.0.e);
+        }
+    }
+
+    #[test]
+    fn solid_is_the_same_everywhere() {
+        let background = Background::Solid(Color::new(0.1, 0.2, 0.3));
+        for direction in [Vec3::new(0.0, 1.0, 0.0), Vec3::new(-3.0, -4.0, 0.5)] {
+            assert_color(background.value(&direction), [0.1, 0.2, 0.3]);
+        }
+        assert!(background.sample().is_none());
+        assert_eq!(background.pdf(&Vec3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn gradient_blends_by_height() {
+        let background = Background::Gradient {
+            bottom: Color::new(1.0, 0.0, 0.0),
+            top: Color::new(0.0, 0.0, 1.0),
+        };
+        // Directions need not be unit length.
+        assert_color(
+            background.value(&Vec3::new(0.0, -5.0, 0.0)),
+            [1.0, 0.0, 0.0],
+        );
+        assert_color(background.value(&Vec3::new(0.0, 0.1, 0.0)), [0.0, 0.0, 1.0]);
+        assert_color(background.value(&Vec3::new(2.0, 0.0, 2.0)), [0.5, 0.0, 0.5]);
+        assert!(background.sample().is_none());
+
+        // The default is the original sky.
+        let sky = Background::default();
+        assert_color(sky.value(&Vec3::new(0.0, -1.0, 0.0)), [1.0; 3]);
+        assert_color(sky.value(&Vec3::new(0.0, 1.0, 0.0)), [0.5, 0.7, 1.0]);
+    }
+
+    #[test]
+    fn environments_are_looked_up_and_sampled() {
+        let background = Background::Environment(Arc::new(Zenith));
+        assert_color(background.value(&Vec3::new(0.0, 2.0, 0.0)), [1.0; 3]);
+        assert_color(background.value(&Vec3::new(1.0, -1.0, 0.0)), [0.0; 3]);
+        let (direction, pdf) = background.sample().unwrap();
+        assert_eq!((direction.e, pdf), ([0.0, 1.0, 0.0], 2.0));
+        assert_eq!(background.pdf(&Vec3::new(0.0, -1.0, 0.0)), 0.0);
+    }
+}
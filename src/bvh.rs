@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    ray::Ray,
+    rtweekend::random_f64,
+    vec3::{Point3, Vec3},
+};
+
+/// Number of centroid buckets evaluated by the surface area heuristic.
+const SAH_BUCKETS: usize = 12;
+
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+    /// Objects under this node, and how many of them are on the left, so
+    /// light sampling can pick among them all evenly.
+    count: usize,
+    left_count: usize,
+}
+
+impl BvhNode {
+    pub fn from_list(list: HittableList) -> Self {
+        let mut objects = list.objects().to_vec();
+        Self::new(&mut objects)
+    }
+
+    pub fn new(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let count = objects.len();
+        let (left, right, left_count): (Arc<dyn Hittable>, Arc<dyn Hittable>, _) = match count {
+            0 => {
+                let empty: Arc<dyn Hittable> = Arc::new(HittableList::<dyn Hittable>::default());
+                (empty.clone(), empty, 0)
+            }
+            1 => (objects[0].clone(), objects[0].clone(), 1),
+            2 => (objects[0].clone(), objects[1].clone(), 1),
+            _ => {
+                let mid = Self::split(objects);
+                let (lower, upper) = objects.split_at_mut(mid);
+                (Arc::new(Self::new(lower)), Arc::new(Self::new(upper)), mid)
+            }
+        };
+
+        let bbox = Aabb::from_boxes(&left.bounding_box(), &right.bounding_box());
+        Self {
+            left,
+            right,
+            bbox,
+            count,
+            left_count,
+        }
+    }
+
+    /// Sorts `objects` along the widest centroid axis and returns the index that
+    /// minimizes the surface area heuristic cost of the two halves.
+    fn split(objects: &mut [Arc<dyn Hittable>]) -> usize {
+        let centroid_bounds = objects.iter().fold(Aabb::EMPTY, |bounds, object| {
+            let c = object.bounding_box().centroid();
+            Aabb::from_boxes(&bounds, &Aabb::from_points(&c, &c))
+        });
+        let axis = centroid_bounds.longest_axis();
+        let axis_bounds = *centroid_bounds.axis_interval(axis);
+
+        objects.sort_by(|a, b| {
+            let a = a.bounding_box().centroid().e[axis];
+            let b = b.bounding_box().centroid().e[axis];
+            a.total_cmp(&b)
+        });
+
+        let n = objects.len();
+        if axis_bounds.size() <= 0.0 {
+            return n / 2;
+        }
+
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut bounds = [Aabb::EMPTY; SAH_BUCKETS];
+        for object in objects.iter() {
+            let bbox = object.bounding_box();
+            let offset = (bbox.centroid().e[axis] - axis_bounds.min) / axis_bounds.size();
+            let b = ((offset * SAH_BUCKETS as f64) as usize).min(SAH_BUCKETS - 1);
+            counts[b] += 1;
+            bounds[b] = Aabb::from_boxes(&bounds[b], &bbox);
+        }
+
+        // Cost of splitting after bucket i, for every i that leaves both sides non-empty.
+        let mut best_bucket = 0;
+        let mut best_cost = f64::INFINITY;
+        for i in 0..SAH_BUCKETS - 1 {
+            let (left_count, left_box) = (0..=i).fold((0, Aabb::EMPTY), |(c, b), k| {
+                (c + counts[k], Aabb::from_boxes(&b, &bounds[k]))
+            });
+            let (right_count, right_box) = (i + 1..SAH_BUCKETS)
+                .fold((0, Aabb::EMPTY), |(c, b), k| {
+                    (c + counts[k], Aabb::from_boxes(&b, &bounds[k]))
+                });
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+
+            let cost = left_count as f64 * left_box.surface_area()
+                + right_count as f64 * right_box.surface_area();
+            if cost < best_cost {
+                best_cost = cost;
+                best_bucket = i;
+            }
+        }
+
+        // An unbounded object, such as global fog, makes every split cost
+        // infinity.
+        if best_cost.is_infinite() {
+            return n / 2;
+        }
+
+        // Objects are sorted by centroid, so bucket membership is monotonic in index.
+        counts[..=best_bucket].iter().sum()
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(r, ray_t) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(r, ray_t);
+        // A lone object sits on both sides; testing a medium twice would
+        // give it two chances to scatter.
+        if Arc::ptr_eq(&self.left, &self.right) {
+            return hit_left;
+        }
+        let closest_so_far = hit_left.as_ref().map_or(ray_t.max, |rec| rec.t);
+        let hit_right = self.right.hit(r, Interval::new(ray_t.min, closest_so_far));
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        if !self.bbox.hit(r, ray_t) {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(r, ray_t);
+        if left <= 0.0 || Arc::ptr_eq(&self.left, &self.right) {
+            return left;
+        }
+        left * self.right.transmittance(r, ray_t)
+    }
+
+    /// The same density as a flat [`HittableList`] of the objects: the
+    /// average of theirs.
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        if self.count == 0 || !self.bbox.hit(r, Interval::new(0.001, f64::INFINITY)) {
+            return 0.0;
+        }
+        if Arc::ptr_eq(&self.left, &self.right) {
+            return self.left.pdf_value(r);
+        }
+        let left = self.left_count as f64 / self.count as f64;
+        left * self.left.pdf_value(r) + (1.0 - left) * self.right.pdf_value(r)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        if Arc::ptr_eq(&self.left, &self.right)
+            || random_f64() * (self.count as f64) < self.left_count as f64
+        {
+            self.left.random(origin, time)
+        } else {
+            self.right.random(origin, time)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{quad::Quad, sphere::Sphere};
+
+    /// Spheres spread unevenly through a box, so the splits are lopsided.
+    fn spheres() -> HittableList {
+        let mut list = HittableList::default();
+        for i in 0..40 {
+            let k = i as f64;
+            let center = Point3::new(
+                (k * 7.3) % 10.0,
+                (k * k * 0.37) % 6.0,
+                (k * 2.9) % 4.0 - 2.0,
+            );
+            let radius = 0.2 + (k % 5.0) * 0.1;
+            list.add(Arc::new(Sphere::new(&center, radius, None)) as Arc<dyn Hittable>);
+        }
+        list
+    }
+
+    /// Rays fanning out from a point in front of the spheres.
+    fn rays() -> impl Iterator<Item = Ray> {
+        (0..400).map(|i| {
+            let (u, v) = ((i % 20) as f64 / 19.0, (i / 20) as f64 / 19.0);
+            let origin = Point3::new(5.0, 3.0, 12.0);
+            let target = Point3::new(12.0 * u - 1.0, 8.0 * v - 1.0, 0.0);
+            Ray::new(&origin, &(target - origin))
+        })
+    }
+
+    #[test]
+    fn hits_match_the_flat_list() {
+        let list = spheres();
+        let bvh = BvhNode::from_list(spheres());
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut hits = 0;
+        for r in rays() {
+            let expected = list.hit(&r, ray_t);
+            let found = bvh.hit(&r, ray_t);
+            assert_eq!(expected.is_some(), found.is_some());
+            if let (Some(expected), Some(found)) = (expected, found) {
+                assert_eq!(expected.t, found.t);
+                hits += 1;
+            }
+            let (expected, found) = (list.pdf_value(&r), bvh.pdf_value(&r));
+            assert!(
+                (expected - found).abs() <= 1e-12 * expected,
+                "{expected} vs {found}"
+            );
+        }
+        assert!(hits > 40, "only {hits} rays hit");
+    }
+
+    #[test]
+    fn split_separates_clusters() {
+        // Three small boxes near the origin and two far along +Y.
+        let mut objects: Vec<Arc<dyn Hittable>> = [9.0, 0.0, 10.0, 0.5, 1.0]
+            .map(|y| -> Arc<dyn Hittable> {
+                Arc::new(Sphere::new(&Point3::new(0.0, y, 0.0), 0.1, None))
+            })
+            .to_vec();
+        assert_eq!(BvhNode::split(&mut objects), 3);
+        let ys: Vec<f64> = objects
+            .iter()
+            .map(|o| o.bounding_box().centroid().y())
+            .collect();
+        assert_eq!(ys, [0.0, 0.5, 1.0, 9.0, 10.0]);
+    }
+
+    #[test]
+    fn samples_every_light_evenly() {
+        // Three lights, split two and one, each with its own direction.
+        let quad = |x: f64| -> Arc<dyn Hittable> {
+            Arc::new(Quad::new(
+                &Point3::new(x, 0.0, 0.0),
+                &Vec3::new(1.0, 0.0, 0.0),
+                &Vec3::new(0.0, 1.0, 0.0),
+                None,
+            ))
+        };
+        let bvh = BvhNode::new(&mut [quad(0.0), quad(2.0), quad(20.0)]);
+        let origin = Point3::new(0.5, 0.5, 5.0);
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            let direction = bvh.random(&origin, 0.0);
+            let x = (origin + 5.0 * direction / direction.z().abs()).x();
+            counts[if x < 1.0 {
+                0
+            } else if x < 3.0 {
+                1
+            } else {
+                2
+            }] += 1;
+        }
+        for count in counts {
+            assert!((800..1200).contains(&count), "{counts:?}");
+        }
+    }
+}
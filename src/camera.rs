@@ -1,9 +1,18 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+};
+
 use crate::{
-    color::{Color, write_color},
-    hittable::Hittable,
+    background::Background,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    image::Image,
     interval::Interval,
+    material::Scatter,
+    pdf::Pdf,
     ray::Ray,
-    rtweekend::{degrees_to_radians, random_f64},
+    rtweekend::{degrees_to_radians, random_f64, seed_rng},
     vec3::{Point3, Vec3, random_in_unit_disk, unit_vector},
 };
 
@@ -18,6 +27,16 @@ pub struct CameraBuilder {
     pub vup: Vec3,
     pub defocus_angle: f64,
     pub focus_dist: f64,
+    /// The exposure, within the 0..=1 range over which moving objects move.
+    /// Each camera ray is cast at a random time between the two.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    /// Seen, and lighting the scene, wherever rays escape.
+    pub background: Background,
+    /// Worker threads used by `render`; 0 uses every available core.
+    pub threads: usize,
+    /// Makes renders repeatable: each scanline samples from its own seeded sequence.
+    pub seed: Option<u64>,
 }
 
 impl Default for CameraBuilder {
@@ -33,16 +52,26 @@ impl Default for CameraBuilder {
             vup: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            background: Background::default(),
+            threads: 0,
+            seed: None,
         }
     }
 }
 
 impl CameraBuilder {
-    pub fn aspect_ration(mut self, aspect_ratio: f64) -> CameraBuilder {
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> CameraBuilder {
         self.aspect_ratio = aspect_ratio;
         self
     }
 
+    #[deprecated(note = "renamed to `aspect_ratio`")]
+    pub fn aspect_ration(self, aspect_ratio: f64) -> CameraBuilder {
+        self.aspect_ratio(aspect_ratio)
+    }
+
     pub fn image_width(mut self, image_width: u32) -> CameraBuilder {
         self.image_width = image_width;
         self
@@ -88,6 +117,31 @@ impl CameraBuilder {
         self
     }
 
+    pub fn shutter_open(mut self, shutter_open: f64) -> CameraBuilder {
+        self.shutter_open = shutter_open;
+        self
+    }
+
+    pub fn shutter_close(mut self, shutter_close: f64) -> CameraBuilder {
+        self.shutter_close = shutter_close;
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> CameraBuilder {
+        self.background = background;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> CameraBuilder {
+        self.threads = threads;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> CameraBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
     pub fn build(&self) -> Camera {
         let aspect_ratio = self.aspect_ratio;
         let image_width = self.image_width;
@@ -99,6 +153,11 @@ impl CameraBuilder {
         let vup = self.vup;
         let defocus_angle = self.defocus_angle;
         let focus_dist = self.focus_dist;
+        let shutter_open = self.shutter_open;
+        let shutter_close = self.shutter_close;
+        let background = self.background.clone();
+        let threads = self.threads;
+        let seed = self.seed;
 
         // image
         let image_height = (image_width as f64 / aspect_ratio) as u32;
@@ -147,41 +206,48 @@ impl CameraBuilder {
             vup,
             defocus_angle,
             focus_dist,
+            shutter_open,
+            shutter_close,
+            background,
+            threads,
+            seed,
             image_height,
             pixel_samples_scale: 1.0 / samples_per_pixel as f64,
             center,
             pixel00_loc,
             pixel_delta_u,
             pixel_delta_v,
-            u,
-            v,
-            w,
             defocus_disk_u,
             defocus_disk_v,
         }
     }
 }
 
+/// A camera ready to render, with the viewport derived from its settings.
+/// The settings are read-only here; change them on a [`CameraBuilder`] and
+/// build again.
 pub struct Camera {
-    pub aspect_ratio: f64,
-    pub image_width: u32,
-    pub samples_per_pixel: u32,
-    pub max_depth: u32,
-    pub vfov: f64,
+    aspect_ratio: f64,
+    image_width: u32,
+    samples_per_pixel: u32,
+    max_depth: u32,
+    vfov: f64,
     lookfrom: Point3,
     lookat: Point3,
     vup: Vec3,
     defocus_angle: f64,
     focus_dist: f64,
+    shutter_open: f64,
+    shutter_close: f64,
+    background: Background,
+    threads: usize,
+    seed: Option<u64>,
     image_height: u32,
     pixel_samples_scale: f64,
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
 }
@@ -191,23 +257,129 @@ impl Camera {
         CameraBuilder::default()
     }
 
-    pub fn render(&mut self, world: &impl Hittable) {
-        println!("P3");
-        println!("{} {}", self.image_width, self.image_height);
-        println!("255");
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    pub fn image_width(&self) -> u32 {
+        self.image_width
+    }
+
+    /// The image width over the aspect ratio, at least one pixel.
+    pub fn image_height(&self) -> u32 {
+        self.image_height
+    }
+
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    pub fn vfov(&self) -> f64 {
+        self.vfov
+    }
+
+    pub fn lookfrom(&self) -> Point3 {
+        self.lookfrom
+    }
+
+    pub fn lookat(&self) -> Point3 {
+        self.lookat
+    }
+
+    pub fn vup(&self) -> Vec3 {
+        self.vup
+    }
+
+    pub fn defocus_angle(&self) -> f64 {
+        self.defocus_angle
+    }
+
+    pub fn focus_dist(&self) -> f64 {
+        self.focus_dist
+    }
+
+    pub fn shutter_open(&self) -> f64 {
+        self.shutter_open
+    }
+
+    pub fn shutter_close(&self) -> f64 {
+        self.shutter_close
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
 
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
+    /// Renders `world`, sampling `lights` directly from every diffuse hit.
+    /// Emitters left out of `lights` still shine, but only where paths happen
+    /// to find them.
+    pub fn render(&self, world: &impl Hittable, lights: &impl Hittable) -> Image {
+        let mut image = Image::new(self.image_width, self.image_height);
+
+        // Scanlines are handed out one at a time from a shared counter, so fast
+        // threads keep picking up work while slow rows are still being traced.
+        let next_row = AtomicU32::new(0);
+        let rows_remaining = AtomicU32::new(self.image_height);
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+
+        let rendered_rows: Vec<(u32, Vec<Color>)> = thread::scope(|s| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut rows = Vec::new();
+                        loop {
+                            let j = next_row.fetch_add(1, Ordering::Relaxed);
+                            if j >= self.image_height {
+                                break;
+                            }
+                            rows.push((j, self.render_row(j, world, lights)));
+
+                            let remaining = rows_remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                            eprintln!("\rScanlines remaining: {} ", remaining);
+                        }
+                        rows
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        for (j, row) in rendered_rows {
+            image.row_mut(j).copy_from_slice(&row);
+        }
+        eprintln!("\rDone.");
+
+        image
+    }
+
+    fn render_row(&self, j: u32, world: &impl Hittable, lights: &impl Hittable) -> Vec<Color> {
+        if let Some(seed) = self.seed {
+            // Derive a distinct sequence per row so the image doesn't depend on
+            // which thread happened to trace it.
+            seed_rng(seed ^ (j as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        }
+
+        (0..self.image_width)
+            .map(|i| {
                 let mut pixel_color = Color::default();
                 for _ in 0..self.samples_per_pixel {
                     let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&r, self.max_depth, world);
+                    pixel_color += self.ray_color(&r, self.max_depth, world, lights);
                 }
-                write_color(&(self.pixel_samples_scale * pixel_color));
-            }
-        }
-        eprintln!("\rDone.");
+                self.pixel_samples_scale * pixel_color
+            })
+            .collect()
     }
 
     fn get_ray(&self, i: u32, j: u32) -> Ray {
@@ -222,8 +394,13 @@ impl Camera {
             self.defocus_disk_sample()
         };
         let ray_dir = pixel_sample - ray_origin;
+        let ray_time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + (self.shutter_close - self.shutter_open) * random_f64()
+        } else {
+            self.shutter_open
+        };
 
-        Ray::new(&ray_origin, &ray_dir)
+        Ray::with_time(&ray_origin, &ray_dir, ray_time)
     }
 
     fn sample_square() -> Vec3 {
@@ -235,26 +412,175 @@ impl Camera {
         self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
     }
 
-    fn ray_color(&self, r: &Ray, depth: u32, world: &impl Hittable) -> Color {
-        if depth <= 0 {
+    fn ray_color(
+        &self,
+        r: &Ray,
+        depth: u32,
+        world: &impl Hittable,
+        lights: &impl Hittable,
+    ) -> Color {
+        self.trace(r, depth, world, lights, None)
+    }
+
+    /// `sampling_pdf` is the density with which the previous bounce chose
+    /// `r`, if that bounce also sampled the lights and the environment; what
+    /// `r` finds of either is then weighted against those light samples.
+    fn trace(
+        &self,
+        r: &Ray,
+        depth: u32,
+        world: &impl Hittable,
+        lights: &impl Hittable,
+        sampling_pdf: Option<f64>,
+    ) -> Color {
+        if depth == 0 {
             return Color(Vec3::new(0.0, 0.0, 0.0));
         }
 
-        if let Some(rec) = world.hit(r, Interval::new(0.001, std::f64::INFINITY)) {
-            // let direction = rec.normal + random_unit_vector();
-            // return 0.5 * self.ray_color(&Ray::new(&rec.p, &direction), depth - 1, world);
-            if let Some(ref mat) = rec.mat {
-                if let Some((attenuation, scattered)) = mat.scatter(r, &rec) {
-                    return attenuation * self.ray_color(&scattered, depth - 1, world);
-                }
-            }
+        let Some(rec) = world.hit(r, Interval::new(0.001, f64::INFINITY)) else {
+            let background = self.background.value(r.direction());
+            return match sampling_pdf {
+                Some(pdf) => power_heuristic(pdf, self.background.pdf(r.direction())) * background,
+                None => background,
+            };
+        };
+        let Some(ref mat) = rec.mat else {
             return Color(Vec3::new(0.0, 0.0, 0.0));
+        };
+
+        let mut color_from_emission = mat.emitted(&rec);
+        if let Some(pdf) = sampling_pdf
+            && color_from_emission.0.length_squared() > 0.0
+        {
+            // Only the lights could also have been reached by a light sample;
+            // other emitters are found by paths alone and keep their weight.
+            let light_pdf = if Self::is_light_hit(r, &rec, lights) {
+                lights.pdf_value(r)
+            } else {
+                0.0
+            };
+            color_from_emission = power_heuristic(pdf, light_pdf) * color_from_emission;
+        }
+        let Some(srec) = mat.scatter(r, &rec) else {
+            return color_from_emission;
+        };
+        let pdf = match srec.scatter {
+            Scatter::Specular(scattered) => {
+                return color_from_emission
+                    + srec.attenuation * self.trace(&scattered, depth - 1, world, lights, None);
+            }
+            Scatter::Pdf(pdf) => pdf,
+        };
+
+        let scattered = Ray::with_time(&rec.p, &pdf.generate(), r.time());
+        let pdf_value = pdf.value(scattered.direction());
+        let scattering_pdf = mat.scattering_pdf(r, &rec, &scattered);
+        let color_from_scatter = if pdf_value > 0.0 && scattering_pdf > 0.0 {
+            scattering_pdf / pdf_value
+                * self.trace(&scattered, depth - 1, world, lights, Some(pdf_value))
+        } else {
+            Color::default()
+        };
+
+        let direct = self.sample_lights(r, &rec, pdf.as_ref(), world, lights)
+            + self.sample_environment(r, &rec, pdf.as_ref(), world);
+        color_from_emission + srec.attenuation * (direct + color_from_scatter)
+    }
+
+    /// Whether `rec`, found along `r`, is the point of the `lights` that a
+    /// light sample in the same direction would have arrived at.
+    fn is_light_hit(r: &Ray, rec: &HitRecord, lights: &impl Hittable) -> bool {
+        lights
+            .hit(r, Interval::new(0.001, f64::INFINITY))
+            .is_some_and(|light_rec| (light_rec.t - rec.t).abs() <= 1e-9 * rec.t.max(1.0))
+    }
+
+    /// Light reaching `rec` from a random point on one of the `lights`, per
+    /// unit of the hit material's attenuation. `pdf` is the distribution the
+    /// path itself continues with, which could also have found the light.
+    fn sample_lights(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        pdf: &dyn Pdf,
+        world: &impl Hittable,
+        lights: &impl Hittable,
+    ) -> Color {
+        let black = Color(Vec3::new(0.0, 0.0, 0.0));
+        let Some(ref mat) = rec.mat else {
+            return black;
+        };
+
+        let to_light = lights.random(&rec.p, r.time());
+        if to_light.near_zero() {
+            return black;
+        }
+        let shadow_ray = Ray::with_time(&rec.p, &unit_vector(&to_light), r.time());
+        let light_pdf = lights.pdf_value(&shadow_ray);
+        if light_pdf <= 0.0 {
+            return black;
+        }
+        let scattering_pdf = mat.scattering_pdf(r, rec, &shadow_ray);
+        if scattering_pdf <= 0.0 {
+            return black;
         }
 
-        let unit_direction = unit_vector(r.direction());
-        let a = 0.5 * (unit_direction.y() + 1.0) as f64;
+        // The point of the light the shadow ray arrives at, and whatever
+        // stands between it and `rec`.
+        let Some(light_rec) = lights.hit(&shadow_ray, Interval::new(0.001, f64::INFINITY)) else {
+            return black;
+        };
+        let Some(ref light_mat) = light_rec.mat else {
+            return black;
+        };
+        let transmittance =
+            world.transmittance(&shadow_ray, Interval::new(0.001, light_rec.t - 0.001));
+        if transmittance <= 0.0 {
+            return black;
+        }
+
+        let emitted = light_mat.emitted(&light_rec);
+        let weight = power_heuristic(light_pdf, pdf.value(shadow_ray.direction())) * scattering_pdf
+            / light_pdf;
+        weight * transmittance * emitted
+    }
+
+    /// Light reaching `rec` along one direction sampled from the environment,
+    /// per unit of the hit material's attenuation. `pdf` is the distribution
+    /// the path itself continues with, which could also have found the light.
+    fn sample_environment(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        pdf: &dyn Pdf,
+        world: &impl Hittable,
+    ) -> Color {
+        let black = Color(Vec3::new(0.0, 0.0, 0.0));
+        let Some((direction, light_pdf)) = self.background.sample() else {
+            return black;
+        };
+        let Some(ref mat) = rec.mat else {
+            return black;
+        };
+
+        let shadow_ray = Ray::with_time(&rec.p, &direction, r.time());
+        let scattering_pdf = mat.scattering_pdf(r, rec, &shadow_ray);
+        if scattering_pdf <= 0.0 {
+            return black;
+        }
+        let transmittance = world.transmittance(&shadow_ray, Interval::new(0.001, f64::INFINITY));
+        if transmittance <= 0.0 {
+            return black;
+        }
 
-        // background: blend white and blue
-        Color((1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0))
+        let weight = power_heuristic(light_pdf, pdf.value(&direction)) * scattering_pdf / light_pdf;
+        weight * transmittance * self.background.value(&direction)
     }
 }
+
+/// Veach's power heuristic: the weight of a sample drawn with density `pdf`
+/// when `other_pdf` could also have produced it.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 { 0.0 } else { a / (a + b) }
+}
@@ -0,0 +1,345 @@
+use std::path::PathBuf;
+
+use ray_tracing::{Background, CameraBuilder, Color, ImageFormat, Vec3, scenes};
+
+pub const USAGE: &str = "\
+Usage: ray_tracing [OPTIONS]
+
+Scene and output:
+  -s, --scene <NAME|FILE>      built-in scene or TOML scene file [default: random-spheres]
+  -o, --output <FILE>          write the image to FILE instead of stdout
+  -f, --format <FORMAT>        p3, p6, png, hdr or pfm [default: from the output
+                               extension, or p3 on stdout]
+  -j, --threads <N>            worker threads, 0 for all cores [default: 0]
+      --seed <N>               seed scene generation and sampling for repeatable renders
+
+Camera overrides (default to the scene's values):
+      --aspect-ratio <RATIO>   e.g. 1.7778 or 16/9
+      --width <PIXELS>         image width
+      --spp <N>                samples per pixel
+      --max-depth <N>          maximum ray bounces
+      --vfov <DEGREES>         vertical field of view
+      --lookfrom <X,Y,Z>       camera position
+      --lookat <X,Y,Z>         point the camera looks at
+      --vup <X,Y,Z>            camera-relative up direction
+      --defocus-angle <DEG>    aperture cone angle, 0 for a pinhole
+      --focus-dist <DIST>      distance to the plane of perfect focus
+      --background <R,G,B>     solid background color, e.g. 0,0,0 for none
+
+  -h, --help                   print this help
+";
+
+pub enum Command {
+    Help,
+    Render(Box<Options>),
+}
+
+pub enum SceneSource {
+    Preset(String),
+    File(PathBuf),
+}
+
+pub struct Options {
+    pub scene: SceneSource,
+    pub output: Option<PathBuf>,
+    pub format: ImageFormat,
+    pub seed: Option<u64>,
+    threads: Option<usize>,
+    camera: CameraOverrides,
+}
+
+/// `CameraBuilder` fields given on the command line.
+#[derive(Default)]
+struct CameraOverrides {
+    aspect_ratio: Option<f64>,
+    image_width: Option<u32>,
+    samples_per_pixel: Option<u32>,
+    max_depth: Option<u32>,
+    vfov: Option<f64>,
+    lookfrom: Option<Vec3>,
+    lookat: Option<Vec3>,
+    vup: Option<Vec3>,
+    defocus_angle: Option<f64>,
+    focus_dist: Option<f64>,
+    background: Option<Vec3>,
+}
+
+impl Options {
+    /// Applies the command-line overrides on top of a scene's camera.
+    pub fn configure(&self, mut camera: CameraBuilder) -> CameraBuilder {
+        let o = &self.camera;
+        if let Some(v) = o.aspect_ratio {
+            camera = camera.aspect_ratio(v);
+        }
+        if let Some(v) = o.image_width {
+            camera = camera.image_width(v);
+        }
+        if let Some(v) = o.samples_per_pixel {
+            camera = camera.samples_per_pixel(v);
+        }
+        if let Some(v) = o.max_depth {
+            camera = camera.max_depth(v);
+        }
+        if let Some(v) = o.vfov {
+            camera = camera.vfov(v);
+        }
+        if let Some(v) = &o.lookfrom {
+            camera = camera.lookfrom(v);
+        }
+        if let Some(v) = &o.lookat {
+            camera = camera.lookat(v);
+        }
+        if let Some(v) = &o.vup {
+            camera = camera.vup(v);
+        }
+        if let Some(v) = o.defocus_angle {
+            camera = camera.defocus_angle(v);
+        }
+        if let Some(v) = o.focus_dist {
+            camera = camera.focus_dist(v);
+        }
+        if let Some(v) = o.background {
+            camera = camera.background(Background::Solid(Color(v)));
+        }
+        if let Some(v) = self.threads {
+            camera = camera.threads(v);
+        }
+        if let Some(v) = self.seed {
+            camera = camera.seed(v);
+        }
+        camera
+    }
+}
+
+/// Parses the arguments that follow the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut scene = SceneSource::Preset(scenes::PRESETS[0].to_string());
+    let mut output = None;
+    let mut format = None;
+    let mut seed = None;
+    let mut threads = None;
+    let mut camera = CameraOverrides::default();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`.
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            match inline_value {
+                Some(v) => Ok(v.to_string()),
+                None => args.next().ok_or_else(|| format!("`{flag}` needs a value")),
+            }
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-s" | "--scene" => {
+                let name = value()?;
+                scene = if scenes::PRESETS.contains(&name.as_str()) {
+                    SceneSource::Preset(name)
+                } else if name.ends_with(".toml") || PathBuf::from(&name).exists() {
+                    SceneSource::File(PathBuf::from(name))
+                } else {
+                    return Err(format!(
+                        "unknown scene `{name}` (built-in scenes: {})",
+                        scenes::PRESETS.join(", ")
+                    ));
+                };
+            }
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => {
+                let name = value()?;
+                format = Some(ImageFormat::from_name(&name).ok_or_else(|| {
+                    format!(
+                        "unknown format `{name}` (expected one of: {})",
+                        ImageFormat::NAMES.join(", ")
+                    )
+                })?);
+            }
+            "-j" | "--threads" => threads = Some(parse_number(&flag, &value()?)?),
+            "--seed" => seed = Some(parse_number(&flag, &value()?)?),
+            "--aspect-ratio" => camera.aspect_ratio = Some(parse_ratio(&flag, &value()?)?),
+            "--width" => camera.image_width = Some(parse_positive_int(&flag, &value()?)?),
+            "--spp" => camera.samples_per_pixel = Some(parse_positive_int(&flag, &value()?)?),
+            "--max-depth" => camera.max_depth = Some(parse_number(&flag, &value()?)?),
+            "--vfov" => camera.vfov = Some(parse_angle(&flag, &value()?)?),
+            "--lookfrom" => camera.lookfrom = Some(parse_vec3(&flag, &value()?)?),
+            "--lookat" => camera.lookat = Some(parse_vec3(&flag, &value()?)?),
+            "--vup" => camera.vup = Some(parse_vec3(&flag, &value()?)?),
+            "--defocus-angle" => {
+                camera.defocus_angle = Some(parse_non_negative(&flag, &value()?)?);
+            }
+            "--focus-dist" => camera.focus_dist = Some(parse_positive(&flag, &value()?)?),
+            "--background" => camera.background = Some(parse_vec3(&flag, &value()?)?),
+            _ => return Err(format!("unrecognized argument `{arg}`")),
+        }
+    }
+
+    let format = match (format, &output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => ImageFormat::from_path(path).ok_or_else(|| {
+            format!(
+                "cannot tell the format of `{}` from its extension; pass --format",
+                path.display()
+            )
+        })?,
+        (None, None) => ImageFormat::Ppm,
+    };
+
+    Ok(Command::Render(Box::new(Options {
+        scene,
+        output,
+        format,
+        seed,
+        threads,
+        camera,
+    })))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{flag}`"))
+}
+
+fn parse_positive_int(flag: &str, value: &str) -> Result<u32, String> {
+    match parse_number(flag, value)? {
+        0 => Err(format!("`{flag}` must be at least 1")),
+        n => Ok(n),
+    }
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<f64, String> {
+    let n: f64 = parse_number(flag, value)?;
+    if !(n.is_finite() && n > 0.0) {
+        return Err(format!("`{flag}` must be greater than zero, found {value}"));
+    }
+    Ok(n)
+}
+
+/// A field of view, which must open wider than nothing and narrower than a
+/// half turn.
+fn parse_angle(flag: &str, value: &str) -> Result<f64, String> {
+    let n: f64 = parse_number(flag, value)?;
+    if !(n > 0.0 && n < 180.0) {
+        return Err(format!(
+            "`{flag}` must be between 0 and 180 degrees, found {value}"
+        ));
+    }
+    Ok(n)
+}
+
+fn parse_non_negative(flag: &str, value: &str) -> Result<f64, String> {
+    let n: f64 = parse_number(flag, value)?;
+    if !(n.is_finite() && n >= 0.0) {
+        return Err(format!("`{flag}` must be zero or more, found {value}"));
+    }
+    Ok(n)
+}
+
+/// Accepts a plain number or a `width/height` fraction.
+fn parse_ratio(flag: &str, value: &str) -> Result<f64, String> {
+    let ratio = match value.split_once('/') {
+        Some((w, h)) => parse_number::<f64>(flag, w)? / parse_number::<f64>(flag, h)?,
+        None => parse_number(flag, value)?,
+    };
+    if !(ratio.is_finite() && ratio > 0.0) {
+        return Err(format!("`{flag}` must be a positive ratio"));
+    }
+    Ok(ratio)
+}
+
+fn parse_vec3(flag: &str, value: &str) -> Result<Vec3, String> {
+    let parts = value
+        .split(',')
+        .map(|part| parse_number(flag, part))
+        .collect::<Result<Vec<f64>, _>>()?;
+    match parts.as_slice() {
+        [x, y, z] => Ok(Vec3::new(*x, *y, *z)),
+        _ => Err(format!("`{flag}` expects three comma-separated numbers")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn camera(args: &[&str]) -> CameraBuilder {
+        match parse_args(args) {
+            Ok(Command::Render(options)) => options.configure(CameraBuilder::default()),
+            Ok(Command::Help) => panic!("{args:?} asked for help"),
+            Err(err) => panic!("{args:?}: {err}"),
+        }
+    }
+
+    #[test]
+    fn camera_overrides() {
+        let camera = camera(&[
+            "--vfov=35",
+            "--defocus-angle",
+            "0",
+            "--focus-dist",
+            "2.5",
+            "--aspect-ratio",
+            "16/9",
+            "--width",
+            "64",
+        ]);
+        assert_eq!(camera.vfov, 35.0);
+        assert_eq!(camera.defocus_angle, 0.0);
+        assert_eq!(camera.focus_dist, 2.5);
+        assert_eq!(camera.aspect_ratio, 16.0 / 9.0);
+        assert_eq!(camera.image_width, 64);
+
+        let background = self::camera(&["--background", "0,0.5,1"]).background;
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(background.value(&up).0.e, [0.0, 0.5, 1.0]);
+        assert_eq!(background.value(&-up).0.e, [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn out_of_range_camera_values_are_rejected() {
+        for args in [
+            ["--vfov", "0"],
+            ["--vfov", "-10"],
+            ["--vfov", "NaN"],
+            ["--vfov", "inf"],
+            ["--vfov", "180"],
+            ["--vfov", "270"],
+            ["--focus-dist", "0"],
+            ["--focus-dist", "nan"],
+            ["--defocus-angle", "-1"],
+            ["--defocus-angle", "NaN"],
+            ["--aspect-ratio", "1/0"],
+            ["--width", "0"],
+        ] {
+            let err = parse_args(&args)
+                .err()
+                .unwrap_or_else(|| panic!("{args:?} parsed"));
+            assert!(err.contains(args[0]), "{err}");
+        }
+    }
+
+    #[test]
+    fn formats_and_flags() {
+        let Ok(Command::Render(options)) = parse_args(&["-o", "out.png", "--seed=4"]) else {
+            panic!("expected options");
+        };
+        assert_eq!(options.format, ImageFormat::Png);
+        assert_eq!(options.seed, Some(4));
+
+        assert!(matches!(parse_args(&["--help"]), Ok(Command::Help)));
+        assert!(parse_args(&["-o", "out.bmp"]).is_err());
+        assert!(parse_args(&["--spp"]).is_err());
+        assert!(parse_args(&["--frobnicate"]).is_err());
+        assert!(parse_args(&["--scene", "no-such-scene"]).is_err());
+    }
+}
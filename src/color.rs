@@ -1,18 +1,27 @@
+use std::io::{self, Write};
+
 use crate::{interval::Interval, vec3::Vec3};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct Color(pub Vec3);
 
-impl Default for Color {
-    fn default() -> Self {
-        Color(Vec3::default())
-    }
-}
-
 impl Color {
     pub fn new(r: f64, g: f64, b: f64) -> Self {
         Color(Vec3::new(r, g, b))
     }
+
+    /// Perceived brightness of a linear Rec. 709 color.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0.x() + 0.7152 * self.0.y() + 0.0722 * self.0.z()
+    }
+}
+
+impl std::ops::Add for Color {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
 }
 
 impl std::ops::AddAssign for Color {
@@ -71,7 +80,18 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     0.0
 }
 
-pub fn write_color(pixel_color: &Color) {
+/// Decodes a component of an sRGB-encoded image, such as an 8-bit texture
+/// scaled to 0..=1, to linear light.
+pub fn srgb_to_linear(srgb_component: f64) -> f64 {
+    if srgb_component <= 0.04045 {
+        srgb_component / 12.92
+    } else {
+        ((srgb_component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Gamma-encodes and clamps a linear color into 8-bit RGB.
+pub fn to_rgb8(pixel_color: &Color) -> [u8; 3] {
     let r = pixel_color.0.x();
     let g = pixel_color.0.y();
     let b = pixel_color.0.z();
@@ -85,5 +105,10 @@ pub fn write_color(pixel_color: &Color) {
     let gbyte = (255.999 * INTENSITY.clamp(g)) as u8;
     let bbyte = (255.999 * INTENSITY.clamp(b)) as u8;
 
-    println!("{} {} {}", rbyte, gbyte, bbyte);
+    [rbyte, gbyte, bbyte]
+}
+
+pub fn write_color(out: &mut impl Write, pixel_color: &Color) -> io::Result<()> {
+    let [rbyte, gbyte, bbyte] = to_rgb8(pixel_color);
+    writeln!(out, "{} {} {}", rbyte, gbyte, bbyte)
 }
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{Isotropic, Material},
+    ray::Ray,
+    rtweekend::random_f64,
+    texture::{SolidColor, Texture},
+    vec3::Vec3,
+};
+
+/// Smoke, fog or mist of uniform density filling the inside of a closed
+/// boundary, or the whole scene.
+///
+/// A ray travelling through the medium scatters after a random free-flight
+/// distance, exponentially distributed so that denser media scatter sooner,
+/// and leaves in a direction chosen by an [`Isotropic`] phase function.
+pub struct ConstantMedium {
+    /// `None` fills all of space.
+    boundary: Option<Arc<dyn Hittable>>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    /// Fills `boundary`, which must be closed but need not be convex.
+    /// `density` is the chance of scattering per unit of distance travelled.
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: &Color) -> Self {
+        Self::from_texture(boundary, density, Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(boundary: Arc<dyn Hittable>, density: f64, tex: Arc<dyn Texture>) -> Self {
+        Self {
+            boundary: Some(boundary),
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::from_texture(tex)),
+        }
+    }
+
+    /// Fog everywhere. No ray escapes an unbounded medium, so the background
+    /// only shows through fog given a boundary such as a large sphere.
+    pub fn global(density: f64, albedo: &Color) -> Self {
+        Self::global_from_texture(density, Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn global_from_texture(density: f64, tex: Arc<dyn Texture>) -> Self {
+        Self {
+            boundary: None,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::from_texture(tex)),
+        }
+    }
+
+    /// Samples a scattering point within `t0..t1`, the parameters of a stretch
+    /// of `r` inside the medium.
+    fn scatter_within(&self, r: &Ray, t0: f64, t1: f64) -> Option<HitRecord> {
+        let ray_length = r.direction().length();
+        let distance_inside = (t1 - t0) * ray_length;
+        let hit_distance = self.neg_inv_density * random_f64().ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t0 + hit_distance / ray_length;
+        Some(HitRecord {
+            t,
+            p: r.at(t),
+            // Arbitrary: the phase function ignores the orientation.
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            front_face: true,
+            mat: Some(self.phase_function.clone()),
+            ..HitRecord::default()
+        })
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let Some(boundary) = &self.boundary else {
+            return self.scatter_within(r, ray_t.min, ray_t.max);
+        };
+
+        // The free flight has no memory, so it can be sampled afresh in each
+        // stretch of the ray inside the boundary.
+        let mut hit = None;
+        for_each_inside(boundary.as_ref(), r, ray_t, |t0, t1| {
+            hit = self.scatter_within(r, t0, t1);
+            hit.is_none()
+        });
+        hit
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match &self.boundary {
+            Some(boundary) => boundary.bounding_box(),
+            None => Aabb::UNIVERSE,
+        }
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let mut distance = 0.0;
+        match &self.boundary {
+            Some(boundary) => for_each_inside(boundary.as_ref(), r, ray_t, |t0, t1| {
+                distance += t1 - t0;
+                true
+            }),
+            None => distance = ray_t.size(),
+        }
+        (distance * r.direction().length() / self.neg_inv_density).exp()
+    }
+}
+
+/// Calls `f` with the parameters at which `r` enters and leaves each stretch
+/// of the closed `boundary` within `ray_t`, nearest first, until `f` returns
+/// `false`.
+fn for_each_inside(
+    boundary: &dyn Hittable,
+    r: &Ray,
+    ray_t: Interval,
+    mut f: impl FnMut(f64, f64) -> bool,
+) {
+    let mut search_from = f64::NEG_INFINITY;
+    loop {
+        let Some(enter) = boundary.hit(r, Interval::new(search_from, f64::INFINITY)) else {
+            return;
+        };
+        let Some(exit) = boundary.hit(r, Interval::new(enter.t + 0.0001, f64::INFINITY)) else {
+            return;
+        };
+        if enter.t >= ray_t.max {
+            return;
+        }
+
+        let t0 = enter.t.max(ray_t.min);
+        let t1 = exit.t.min(ray_t.max);
+        if t0 < t1 && !f(t0, t1) {
+            return;
+        }
+        search_from = exit.t + 0.0001;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bvh::BvhNode, hittable_list::HittableList, sphere::Sphere, vec3::Point3};
+
+    const SAMPLES: usize = 20_000;
+
+    fn fog(boundary: Arc<dyn Hittable>, density: f64) -> ConstantMedium {
+        ConstantMedium::new(boundary, density, &Color::new(0.5, 0.5, 0.5))
+    }
+
+    fn ball(x: f64) -> Arc<dyn Hittable> {
+        Arc::new(Sphere::new(&Point3::new(x, 0.0, 0.0), 1.0, None))
+    }
+
+    /// Along the X axis from x = -5, through whatever lies on it.
+    fn along_x() -> Ray {
+        Ray::new(&Point3::new(-5.0, 0.0, 0.0), &Vec3::new(2.0, 0.0, 0.0))
+    }
+
+    fn ray_t() -> Interval {
+        Interval::new(0.001, f64::INFINITY)
+    }
+
+    /// The fraction of rays that scatter in `medium`.
+    fn hit_fraction(medium: &dyn Hittable, r: &Ray) -> f64 {
+        let hits = (0..SAMPLES)
+            .filter(|_| medium.hit(r, ray_t()).is_some())
+            .count();
+        hits as f64 / SAMPLES as f64
+    }
+
+    #[test]
+    fn transmittance_falls_off_with_the_distance_inside() {
+        // Two unit balls make a boundary that the ray enters twice.
+        let mut balls = HittableList::default();
+        balls.add(ball(0.0));
+        balls.add(ball(3.0));
+        let medium = fog(Arc::new(balls), 0.5);
+        let through_both = medium.transmittance(&along_x(), ray_t());
+        assert!(
+            (through_both - (-2.0f64).exp()).abs() < 1e-9,
+            "{through_both}"
+        );
+
+        // Stopping halfway through the first ball.
+        let halfway = medium.transmittance(&along_x(), Interval::new(0.001, 2.5));
+        assert!((halfway - (-0.5f64).exp()).abs() < 1e-9, "{halfway}");
+
+        let global = ConstantMedium::global(0.25, &Color::new(1.0, 1.0, 1.0));
+        let t = global.transmittance(&along_x(), Interval::new(0.0, 3.0));
+        assert!((t - (-1.5f64).exp()).abs() < 1e-12, "{t}");
+    }
+
+    #[test]
+    fn scattering_matches_the_transmittance() {
+        let medium = fog(ball(0.0), 0.7);
+        let r = along_x();
+        let expected = 1.0 - medium.transmittance(&r, ray_t());
+        let found = hit_fraction(&medium, &r);
+        assert!((found - expected).abs() < 0.02, "{found} vs {expected}");
+
+        for _ in 0..100 {
+            if let Some(rec) = medium.hit(&r, ray_t()) {
+                assert!(rec.p.length() <= 1.0 + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn free_flights_are_exponential() {
+        // In fog everywhere, the mean distance to scattering is 1 / density.
+        let medium = ConstantMedium::global(2.0, &Color::new(1.0, 1.0, 1.0));
+        let r = along_x();
+        let mean = (0..SAMPLES)
+            .map(|_| medium.hit(&r, ray_t()).unwrap().t * r.direction().length())
+            .sum::<f64>()
+            / SAMPLES as f64;
+        assert!((mean - 0.5).abs() < 0.02, "{mean}");
+    }
+
+    #[test]
+    fn global_fog_fills_the_universe() {
+        let global = ConstantMedium::global(0.25, &Color::new(1.0, 1.0, 1.0));
+        let b = global.bounding_box();
+        for axis in 0..3 {
+            assert_eq!(b.axis_interval(axis).min, f64::NEG_INFINITY);
+            assert_eq!(b.axis_interval(axis).max, f64::INFINITY);
+        }
+        assert_eq!(fog(ball(3.0), 1.0).bounding_box().x.min, 2.0);
+    }
+
+    #[test]
+    fn a_lone_medium_in_a_bvh_scatters_once() {
+        let medium: Arc<dyn Hittable> = Arc::new(fog(ball(0.0), 0.7));
+        let bvh = BvhNode::new(&mut [medium.clone()]);
+        let r = along_x();
+        let expected = 1.0 - medium.transmittance(&r, ray_t());
+        let found = hit_fraction(&bvh, &r);
+        assert!((found - expected).abs() < 0.02, "{found} vs {expected}");
+        assert_eq!(
+            bvh.transmittance(&r, ray_t()),
+            medium.transmittance(&r, ray_t())
+        );
+    }
+}
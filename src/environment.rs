@@ -0,0 +1,195 @@
+use std::{f64::consts::PI, io, path::Path};
+
+use crate::{
+    background::Environment,
+    color::Color,
+    image::Image,
+    rtweekend::{degrees_to_radians, random_f64},
+    texture::{Filter, ImageTexture, Texture, WrapMode},
+    vec3::{Point3, Vec3, unit_vector},
+};
+
+/// A latitude-longitude (equirectangular) image of everything around the
+/// scene, usually an HDR photo of a real sky or studio.
+///
+/// The top row is straight up (+Y) and the middle column faces -Z, the
+/// default camera's view, before `rotation` turns the map about the Y axis.
+/// Directions are importance sampled in proportion to the map's luminance,
+/// so small bright lights such as the sun are found directly instead of by
+/// chance.
+pub struct EnvironmentMap {
+    texture: ImageTexture,
+    width: usize,
+    height: usize,
+    rotation: f64,
+    intensity: f64,
+    distribution: Option<Distribution>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image) -> Self {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        let distribution = Distribution::new(&image);
+        let texture = ImageTexture::new(image)
+            .with_wrap_uv(WrapMode::Repeat, WrapMode::Clamp)
+            .with_filter(Filter::Bilinear);
+
+        Self {
+            texture,
+            width,
+            height,
+            rotation: 0.0,
+            intensity: 1.0,
+            distribution,
+        }
+    }
+
+    /// Loads an equirectangular image; Radiance .hdr files keep their full
+    /// dynamic range.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(Image::load(path)?))
+    }
+
+    /// Turns the map about the vertical axis, counterclockwise seen from above.
+    pub fn with_rotation(mut self, degrees: f64) -> Self {
+        self.rotation = degrees_to_radians(degrees);
+        self
+    }
+
+    /// Scales the brightness of the whole map.
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Image coordinates of a direction: `u` across from the left edge and
+    /// `v` down from the top row, both in 0..=1.
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = unit_vector(direction);
+        let theta = d.y().clamp(-1.0, 1.0).acos();
+        let phi = d.x().atan2(-d.z()) - self.rotation;
+        let u = (0.5 + phi / (2.0 * PI)).rem_euclid(1.0);
+        (u, theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let theta = v * PI;
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    /// Converts the probability of the pixel under (`u`, `v`) into a density
+    /// over solid angle.
+    fn solid_angle_pdf(&self, probability: f64, v: f64) -> f64 {
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        probability * (self.width * self.height) as f64 / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn value(&self, direction: &Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        // Textures count `v` up from the bottom row.
+        self.intensity * self.texture.value(u, 1.0 - v, &Point3::default())
+    }
+
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        let distribution = self.distribution.as_ref()?;
+        let (i, j, probability) = distribution.sample(random_f64(), random_f64());
+
+        let u = (i as f64 + random_f64()) / self.width as f64;
+        let v = (j as f64 + random_f64()) / self.height as f64;
+        let pdf = self.solid_angle_pdf(probability, v);
+        (pdf > 0.0).then(|| (self.uv_to_direction(u, v), pdf))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f64 {
+        let Some(distribution) = &self.distribution else {
+            return 0.0;
+        };
+        let (u, v) = self.direction_to_uv(direction);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.solid_angle_pdf(distribution.probability(i, j), v)
+    }
+}
+
+/// Piecewise-constant distribution over the map's pixels: a row is chosen
+/// from the marginal CDF, then a column from that row's conditional CDF.
+struct Distribution {
+    width: usize,
+    /// Cumulative row weights, `height + 1` entries from zero to the total.
+    row_cdf: Vec<f64>,
+    /// Cumulative pixel weights within each row, `width + 1` entries per row.
+    column_cdfs: Vec<f64>,
+}
+
+impl Distribution {
+    /// Returns `None` for an all-black map, which has nothing to sample.
+    fn new(image: &Image) -> Option<Self> {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+
+        let mut row_cdf = Vec::with_capacity(height + 1);
+        let mut column_cdfs = Vec::with_capacity(height * (width + 1));
+        row_cdf.push(0.0);
+        for y in 0..height {
+            // Rows near the poles cover less of the sphere.
+            let sin_theta = ((y as f64 + 0.5) / height as f64 * PI).sin();
+            let mut sum = 0.0;
+            column_cdfs.push(0.0);
+            for pixel in image.row(y as u32) {
+                sum += pixel.luminance().max(0.0) * sin_theta;
+                column_cdfs.push(sum);
+            }
+            row_cdf.push(row_cdf[y] + sum);
+        }
+
+        let total = row_cdf[height];
+        (total > 0.0 && total.is_finite()).then_some(Self {
+            width,
+            row_cdf,
+            column_cdfs,
+        })
+    }
+
+    fn total(&self) -> f64 {
+        self.row_cdf[self.row_cdf.len() - 1]
+    }
+
+    fn row(&self, j: usize) -> &[f64] {
+        &self.column_cdfs[j * (self.width + 1)..(j + 1) * (self.width + 1)]
+    }
+
+    fn probability(&self, i: usize, j: usize) -> f64 {
+        let row = self.row(j);
+        (row[i + 1] - row[i]) / self.total()
+    }
+
+    /// Maps two uniform numbers to a pixel and its probability.
+    fn sample(&self, u1: f64, u2: f64) -> (usize, usize, f64) {
+        let j = find_interval(&self.row_cdf, u1 * self.total());
+        let row = self.row(j);
+        let i = find_interval(row, u2 * row[self.width]);
+        (i, j, self.probability(i, j))
+    }
+}
+
+/// The index `k` of the non-empty interval `cdf[k]..cdf[k + 1]` holding `x`.
+fn find_interval(cdf: &[f64], x: f64) -> usize {
+    let k = cdf.partition_point(|&c| c <= x).saturating_sub(1);
+    let mut k = k.min(cdf.len() - 2);
+    // Step back over zero-weight entries so an empty interval is never chosen.
+    while k > 0 && cdf[k + 1] == cdf[k] {
+        k -= 1;
+    }
+    k
+}
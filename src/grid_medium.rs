@@ -0,0 +1,278 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{Material, Scatter, ScatterRecord},
+    pdf::SpherePdf,
+    ray::Ray,
+    rtweekend::random_f64,
+    vec3::{Point3, Vec3},
+    voxel_grid::VoxelGrid,
+};
+
+/// A participating medium whose density varies through an axis-aligned box,
+/// given by a voxel grid spanning it: clouds, smoke plumes and explosions.
+///
+/// Collisions are found by delta tracking against the grid's largest density,
+/// and light through the medium is attenuated by ratio tracking. Albedo and
+/// emission can vary per voxel too.
+pub struct GridMedium {
+    bounds: Aabb,
+    min: Point3,
+    extent: Vec3,
+    density: VoxelGrid<f64>,
+    density_scale: f64,
+    /// An upper bound on the density anywhere in the box.
+    majorant: f64,
+    /// Shared by every collision, which records where in the grids it is.
+    phase: Arc<VoxelPhase>,
+}
+
+impl GridMedium {
+    /// Stretches `density` over the box with opposite corners `a` and `b`.
+    /// Densities are the chance of scattering per unit of distance, so any
+    /// negative values are treated as zero.
+    ///
+    /// Returns `None` if the box has no volume for the grid to fill, being
+    /// flat along some axis or not finite.
+    pub fn new(a: &Point3, b: &Point3, density: VoxelGrid<f64>) -> Option<Self> {
+        let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+        let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+        let extent = max - min;
+        if !extent.e.iter().all(|&d| d > 0.0 && d.is_finite()) {
+            return None;
+        }
+        let majorant = density.max();
+        Some(Self {
+            bounds: Aabb::from_points(&min, &max),
+            min,
+            extent,
+            density,
+            density_scale: 1.0,
+            majorant,
+            phase: Arc::new(VoxelPhase {
+                albedo: VoxelGrid::uniform(Color::new(1.0, 1.0, 1.0)),
+                emission: None,
+            }),
+        })
+    }
+
+    /// Multiplies every density, e.g. to thin out a grid stored as 0..1.
+    pub fn with_density_scale(mut self, scale: f64) -> Self {
+        self.density_scale = scale.max(0.0);
+        self.majorant = self.density_scale * self.density.max();
+        self
+    }
+
+    /// The fraction of light each collision scatters rather than absorbs;
+    /// use [`VoxelGrid::uniform`] for a single color.
+    pub fn with_albedo(mut self, albedo: VoxelGrid<Color>) -> Self {
+        Arc::make_mut(&mut self.phase).albedo = albedo;
+        self
+    }
+
+    /// Light given off within the medium, in proportion to how much it
+    /// absorbs: where it is dense enough to be opaque it glows with this
+    /// radiance, thinner parts glow less, and a white albedo gives no glow.
+    pub fn with_emission(mut self, emission: VoxelGrid<Color>) -> Self {
+        Arc::make_mut(&mut self.phase).emission = Some(emission);
+        self
+    }
+
+    /// `p` in the unit cube the grids are defined over.
+    fn grid_point(&self, p: &Point3) -> Point3 {
+        let d = *p - self.min;
+        Point3::new(
+            d.x() / self.extent.x(),
+            d.y() / self.extent.y(),
+            d.z() / self.extent.z(),
+        )
+    }
+
+    fn density_at(&self, grid_point: &Point3) -> f64 {
+        (self.density_scale * self.density.sample(grid_point)).max(0.0)
+    }
+
+    /// Parameters of the tentative collisions along `r` within `t0..t1`,
+    /// spaced as if the whole box were as dense as the majorant.
+    fn tentative_collisions(&self, r: &Ray, t0: f64, t1: f64) -> impl Iterator<Item = f64> {
+        let rate = self.majorant * r.direction().length();
+        let mut t = t0;
+        std::iter::from_fn(move || {
+            t -= (1.0 - random_f64()).ln() / rate;
+            (t < t1).then_some(t)
+        })
+    }
+}
+
+impl Hittable for GridMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        let inside = self.bounds.clip(r, ray_t)?;
+
+        // Delta tracking: each tentative collision is a real one with the
+        // probability that the local density makes up of the majorant.
+        for t in self.tentative_collisions(r, inside.min, inside.max) {
+            let p = r.at(t);
+            let grid_point = self.grid_point(&p);
+            if random_f64() * self.majorant >= self.density_at(&grid_point) {
+                continue;
+            }
+
+            return Some(HitRecord {
+                t,
+                p,
+                // Arbitrary: the phase function ignores the orientation.
+                normal: Vec3::new(1.0, 0.0, 0.0),
+                front_face: true,
+                mat: Some(self.phase.clone()),
+                u: grid_point.x(),
+                v: grid_point.y(),
+                w: grid_point.z(),
+            });
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        if self.majorant <= 0.0 {
+            return 1.0;
+        }
+        let Some(inside) = self.bounds.clip(r, ray_t) else {
+            return 1.0;
+        };
+
+        // Ratio tracking: rather than stopping at the first real collision,
+        // weigh down by the chance of each tentative collision being real.
+        let mut transmittance = 1.0;
+        for t in self.tentative_collisions(r, inside.min, inside.max) {
+            let density = self.density_at(&self.grid_point(&r.at(t)));
+            transmittance *= 1.0 - density / self.majorant;
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+        transmittance
+    }
+}
+
+/// Isotropic scattering with the albedo and emission of the grids, looked up
+/// where each collision's `u`, `v` and `w` say it is in them.
+#[derive(Clone)]
+struct VoxelPhase {
+    albedo: VoxelGrid<Color>,
+    emission: Option<VoxelGrid<Color>>,
+}
+
+fn grid_point(rec: &HitRecord) -> Point3 {
+    Point3::new(rec.u, rec.v, rec.w)
+}
+
+impl Material for VoxelPhase {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            attenuation: self.albedo.sample(&grid_point(rec)),
+            scatter: Scatter::Pdf(Arc::new(SpherePdf)),
+        })
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    /// Only the absorbed part of each collision glows, so an opaque region's
+    /// scattered and emitted light add up to its emission.
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        let Some(emission) = &self.emission else {
+            return Color::default();
+        };
+        let p = grid_point(rec);
+        let [r, g, b] = self.albedo.sample(&p).0.e.map(|a| (1.0 - a).max(0.0));
+        emission.sample(&p) * Color::new(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Transform;
+
+    /// A box from (0, 0, 0) to (2, 2, 2) too dense to see into, with an
+    /// albedo of 0.2 in its lower half along X and 0.6 in its upper half.
+    fn glowing_box() -> GridMedium {
+        let albedo = [0.2, 0.6].map(|a| Color::new(a, a, a));
+        GridMedium::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(2.0, 2.0, 2.0),
+            VoxelGrid::uniform(1e6),
+        )
+        .unwrap()
+        .with_albedo(VoxelGrid::new([2, 1, 1], albedo.to_vec()))
+        .with_emission(VoxelGrid::uniform(Color::new(1.0, 2.0, 3.0)))
+    }
+
+    /// The albedo and emission where a ray down the Z axis at `x` first
+    /// collides.
+    fn collide(medium: &dyn Hittable, x: f64) -> (HitRecord, Color, Color) {
+        let r = Ray::new(&Point3::new(x, 1.0, 5.0), &Vec3::new(0.0, 0.0, -1.0));
+        let rec = medium
+            .hit(&r, Interval::new(0.001, f64::INFINITY))
+            .expect("the box is opaque");
+        let mat = rec.mat.clone().unwrap();
+        let albedo = mat.scatter(&r, &rec).unwrap().attenuation;
+        let emission = mat.emitted(&rec);
+        (rec, albedo, emission)
+    }
+
+    fn assert_color(found: Color, expected: [f64; 3]) {
+        for (f, e) in found.0.e.into_iter().zip(expected) {
+            assert!((f - e).abs() < 1e-9, "{:?} vs {expected:?}", found.0.e);
+        }
+    }
+
+    #[test]
+    fn collisions_look_up_albedo_and_emission() {
+        let medium = glowing_box();
+        let (low, albedo, emission) = collide(&medium, 0.5);
+        assert!((low.p.z() - 2.0).abs() < 1e-3);
+        assert_color(albedo, [0.2; 3]);
+        assert_color(emission, [0.8, 1.6, 2.4]);
+
+        let (high, albedo, emission) = collide(&medium, 1.5);
+        assert_color(albedo, [0.6; 3]);
+        assert_color(emission, [0.4, 0.8, 1.2]);
+
+        // Every collision shares the one phase material.
+        assert!(Arc::ptr_eq(&low.mat.unwrap(), &high.mat.unwrap()));
+    }
+
+    #[test]
+    fn moved_media_look_up_their_own_grids() {
+        let moved = Transform::translate(Arc::new(glowing_box()), &Vec3::new(10.0, 0.0, 0.0));
+        let (rec, albedo, emission) = collide(&moved.unwrap(), 10.5);
+        assert!((rec.p.x() - 10.5).abs() < 1e-9);
+        assert_color(albedo, [0.2; 3]);
+        assert_color(emission, [0.8, 1.6, 2.4]);
+    }
+
+    #[test]
+    fn flat_boxes_are_refused() {
+        let corner = Point3::new(0.0, 0.0, 0.0);
+        for other in [
+            Point3::new(1.0, 0.0, 1.0),
+            Point3::new(1.0, 1.0, f64::INFINITY),
+            Point3::new(f64::NAN, 1.0, 1.0),
+        ] {
+            assert!(GridMedium::new(&corner, &other, VoxelGrid::uniform(1.0)).is_none());
+        }
+    }
+}
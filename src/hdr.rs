@@ -0,0 +1,353 @@
+use std::io::{self, Read, Write};
+
+use crate::{color::Color, image::Image};
+
+/// Run-length encoded scanlines are only defined for these widths.
+const RLE_WIDTHS: std::ops::RangeInclusive<u32> = 8..=0x7fff;
+
+/// Writes `image` as a Radiance RGBE (.hdr) file with unclamped linear values.
+pub fn write_hdr(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "#?RADIANCE")?;
+    writeln!(out, "FORMAT=32-bit_rle_rgbe")?;
+    writeln!(out)?;
+    writeln!(out, "-Y {} +X {}", image.height(), image.width())?;
+
+    let width = image.width() as usize;
+    let mut scanline = Vec::with_capacity(4 * width);
+    let mut encoded = Vec::with_capacity(4 * width + 4);
+    for y in 0..image.height() {
+        scanline.clear();
+        for pixel_color in image.row(y) {
+            scanline.extend_from_slice(&to_rgbe(pixel_color));
+        }
+
+        if !RLE_WIDTHS.contains(&image.width()) {
+            out.write_all(&scanline)?;
+            continue;
+        }
+
+        // New-style RLE: a marker, then each of the four channels encoded separately.
+        encoded.clear();
+        encoded.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+        for channel in 0..4 {
+            let values: Vec<u8> = scanline.iter().skip(channel).step_by(4).copied().collect();
+            encode_rle_channel(&values, &mut encoded);
+        }
+        out.write_all(&encoded)?;
+    }
+    Ok(())
+}
+
+/// Converts a linear color to shared-exponent RGBE. Negative components are
+/// not representable and become zero.
+pub fn to_rgbe(pixel_color: &Color) -> [u8; 4] {
+    let [r, g, b] = pixel_color.0.e.map(|c| c.max(0.0));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // v = mantissa * 2^exponent with mantissa in [0.5, 1)
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / 2f64.powi(exponent);
+    if mantissa >= 1.0 {
+        mantissa /= 2.0;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+    if exponent > 127 {
+        return [255, 255, 255, 255];
+    }
+
+    let scale = mantissa * 256.0 / v;
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Reads a Radiance RGBE (.hdr) file in the standard `-Y h +X w`
+/// orientation, with flat, old-style or new-style run-length encoded
+/// scanlines.
+pub fn read_hdr(input: &mut impl Read) -> io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    let mut pos = 0;
+
+    let magic = next_line(&data, &mut pos)?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(invalid("not a Radiance file"));
+    }
+    // Header variables end at a blank line.
+    loop {
+        let line = next_line(&data, &mut pos)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format != "32-bit_rle_rgbe"
+        {
+            return Err(invalid(&format!("unsupported format `{format}`")));
+        }
+    }
+
+    let resolution = next_line(&data, &mut pos)?;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => match (w.parse::<u32>(), h.parse::<u32>()) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => (w, h),
+            _ => return Err(invalid("invalid image size")),
+        },
+        _ => {
+            return Err(invalid(&format!(
+                "unsupported resolution line `{resolution}`"
+            )));
+        }
+    };
+
+    // Every scanline starts with at least one four-byte pixel or RLE marker.
+    if height as usize > (data.len() - pos) / 4 {
+        return Err(invalid("pixel data is too short"));
+    }
+    let mut image = Image::try_new(width, height).ok_or_else(|| invalid("image is too large"))?;
+    let mut scanline = vec![[0u8; 4]; width as usize];
+    for y in 0..height {
+        read_scanline(&data, &mut pos, &mut scanline)?;
+        for (pixel, rgbe) in image.row_mut(y).iter_mut().zip(&scanline) {
+            *pixel = from_rgbe(*rgbe);
+        }
+    }
+    Ok(image)
+}
+
+/// Converts shared-exponent RGBE back to a linear color, taking each
+/// mantissa from the middle of its quantization step.
+pub fn from_rgbe([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::default();
+    }
+    let f = 2f64.powi(e as i32 - 136);
+    Color::new(
+        (r as f64 + 0.5) * f,
+        (g as f64 + 0.5) * f,
+        (b as f64 + 0.5) * f,
+    )
+}
+
+fn read_scanline(data: &[u8], pos: &mut usize, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    let truncated = || invalid("pixel data is too short");
+    let byte = |pos: &mut usize| -> io::Result<u8> {
+        let b = *data.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        Ok(b)
+    };
+
+    let head = data.get(*pos..*pos + 4).ok_or_else(truncated)?;
+    let new_rle =
+        RLE_WIDTHS.contains(&(width as u32)) && head[0] == 2 && head[1] == 2 && head[2] & 0x80 == 0;
+    if new_rle {
+        if ((head[2] as usize) << 8 | head[3] as usize) != width {
+            return Err(invalid("scanline width does not match the image"));
+        }
+        *pos += 4;
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = byte(pos)? as usize;
+                if count > 128 {
+                    let run = count - 128;
+                    let value = byte(pos)?;
+                    if x + run > width {
+                        return Err(invalid("run overflows the scanline"));
+                    }
+                    for pixel in &mut scanline[x..x + run] {
+                        pixel[channel] = value;
+                    }
+                    x += run;
+                } else {
+                    if count == 0 || x + count > width {
+                        return Err(invalid("invalid literal run in scanline"));
+                    }
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = byte(pos)?;
+                    }
+                    x += count;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // Flat pixels, where (1, 1, 1, n) repeats the previous pixel n times,
+    // shifted left by 8 bits for each consecutive repeat marker.
+    let mut x = 0;
+    let mut shift = 0;
+    while x < width {
+        let rgbe = [byte(pos)?, byte(pos)?, byte(pos)?, byte(pos)?];
+        if rgbe[..3] == [1, 1, 1] {
+            let previous = *scanline[..x]
+                .last()
+                .ok_or_else(|| invalid("repeat marker at the start of a scanline"))?;
+            // Anything past 24 bits of count could not fit in one scanline.
+            let run = (rgbe[3] as usize) << shift.min(24);
+            if shift > 24 || x + run > width {
+                return Err(invalid("run overflows the scanline"));
+            }
+            scanline[x..x + run].fill(previous);
+            x += run;
+            shift += 8;
+        } else {
+            scanline[x] = rgbe;
+            x += 1;
+            shift = 0;
+        }
+    }
+    Ok(())
+}
+
+fn next_line<'a>(data: &'a [u8], pos: &mut usize) -> io::Result<&'a str> {
+    let rest = &data[*pos..];
+    let len = rest
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| invalid("header ends early"))?;
+    *pos += len + 1;
+    std::str::from_utf8(&rest[..len])
+        .map(str::trim)
+        .map_err(|_| invalid("header is not text"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("HDR: {message}"))
+}
+
+fn encode_rle_channel(values: &[u8], out: &mut Vec<u8>) {
+    const MIN_RUN: usize = 4;
+
+    let mut cur = 0;
+    while cur < values.len() {
+        // Find the next run long enough to be worth encoding.
+        let mut beg_run = cur;
+        let mut run_count = 0;
+        while run_count < MIN_RUN && beg_run < values.len() {
+            beg_run += run_count;
+            run_count = 1;
+            while beg_run + run_count < values.len()
+                && run_count < 127
+                && values[beg_run] == values[beg_run + run_count]
+            {
+                run_count += 1;
+            }
+        }
+
+        // Emit the bytes before the run as literal dumps of at most 128.
+        while cur < beg_run {
+            let count = (beg_run - cur).min(128);
+            out.push(count as u8);
+            out.extend_from_slice(&values[cur..cur + count]);
+            cur += count;
+        }
+
+        if run_count >= MIN_RUN {
+            out.push(128 + run_count as u8);
+            out.push(values[beg_run]);
+            cur += run_count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_image(width: u32) -> Image {
+        let mut image = Image::new(width, 3);
+        for (i, pixel) in image.pixels_mut().iter_mut().enumerate() {
+            // Long runs of one color, broken up by a few distinct pixels.
+            let v = if i % 7 == 0 { i as f64 } else { 0.25 };
+            *pixel = Color::new(v, 2.0 * v, 1000.0);
+        }
+        image
+    }
+
+    fn round_trip(image: &Image) -> Image {
+        let mut bytes = Vec::new();
+        write_hdr(image, &mut bytes).unwrap();
+        read_hdr(&mut bytes.as_slice()).unwrap()
+    }
+
+    fn assert_close(written: &Image, read: &Image) {
+        assert_eq!(
+            (read.width(), read.height()),
+            (written.width(), written.height())
+        );
+        for (a, b) in written.pixels().iter().zip(read.pixels()) {
+            for (a, b) in a.0.e.iter().zip(b.0.e) {
+                // RGBE keeps about 8 bits of mantissa relative to the brightest channel.
+                assert!((a - b).abs() <= 1000.0 / 128.0, "{a} vs {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn run_length_encoded_round_trip() {
+        let image = sample_image(40);
+        assert_close(&image, &round_trip(&image));
+    }
+
+    #[test]
+    fn flat_round_trip() {
+        // Too narrow for run-length encoding.
+        let image = sample_image(5);
+        assert_close(&image, &round_trip(&image));
+    }
+
+    #[test]
+    fn rgbe_conversion() {
+        assert_eq!(to_rgbe(&Color::new(0.0, 0.0, 0.0)), [0; 4]);
+        assert_eq!(to_rgbe(&Color::new(-1.0, 1.0, 0.5)), [0, 128, 64, 129]);
+        assert_eq!(from_rgbe([0, 128, 64, 129]).0.e[1], 128.5 / 128.0);
+        assert_eq!(from_rgbe([255, 255, 255, 0]).0.e, [0.0; 3]);
+    }
+
+    #[test]
+    fn old_style_repeat_markers() {
+        let mut bytes = b"#?RADIANCE\n\n-Y 1 +X 4\n".to_vec();
+        bytes.extend_from_slice(&[128, 64, 32, 129, 1, 1, 1, 3]);
+        let image = read_hdr(&mut bytes.as_slice()).unwrap();
+        assert!(
+            image
+                .pixels()
+                .iter()
+                .all(|c| c.0.e == image.pixel(0, 0).0.e)
+        );
+    }
+
+    #[test]
+    fn huge_header_is_an_error() {
+        let bytes = b"#?RADIANCE\n\n-Y 2000000000 +X 2000000000\n\0\0\0\0";
+        let err = read_hdr(&mut &bytes[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = b"#?RADIANCE\n\n-Y 1 +X 2000000000\n".to_vec();
+        bytes.extend_from_slice(&[128, 64, 32, 129, 1, 1, 1, 255, 1, 1, 1, 255]);
+        assert!(read_hdr(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        for bytes in [
+            &b"P6\n"[..],
+            b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0",
+            b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0",
+            b"#?RADIANCE\n\n-Y 2 +X 1\n\x80\x80\x80\x80",
+            b"#?RADIANCE\n\n-Y 1 +X 2\n\x01\x01\x01\x01\0\0\0\0",
+        ] {
+            assert!(read_hdr(&mut &bytes[..]).is_err());
+        }
+    }
+}
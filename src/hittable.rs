@@ -1,6 +1,7 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
+    aabb::Aabb,
     interval::Interval,
     material::Material,
     ray::Ray,
@@ -11,14 +12,20 @@ use crate::{
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
-    pub mat: Option<Rc<dyn Material>>,
+    pub mat: Option<Arc<dyn Material>>,
     pub t: f64,
+    /// Surface coordinates of the hit point.
+    pub u: f64,
+    pub v: f64,
+    /// With `u` and `v`, where in a volume the hit falls, for materials that
+    /// vary through one; unused on surfaces.
+    pub w: f64,
     pub front_face: bool,
 }
 
 impl HitRecord {
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
-        self.front_face = dot(&r.direction(), outward_normal) < 0.0;
+        self.front_face = dot(r.direction(), outward_normal) < 0.0;
         self.normal = if self.front_face {
             *outward_normal
         } else {
@@ -27,6 +34,31 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord>;
+
+    fn bounding_box(&self) -> Aabb;
+
+    /// The fraction of light carried along `r` within `ray_t` that gets
+    /// through: none past a surface, and some through a participating medium.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        if self.hit(r, ray_t).is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Density over solid angle with which `random` picks the direction of
+    /// `r` from its origin at its time. Zero for objects that cannot be
+    /// sampled as lights.
+    fn pdf_value(&self, _r: &Ray) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` toward a random point of the object, as
+    /// placed at `time`, for sampling it as a light.
+    fn random(&self, _origin: &Point3, _time: f64) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
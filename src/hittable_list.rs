@@ -1,40 +1,62 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     interval::Interval,
     ray::Ray,
+    rtweekend::random_f64,
+    vec3::{Point3, Vec3},
 };
 
-#[derive(Default)]
-pub struct HittableList<T>
+pub struct HittableList<T = dyn Hittable>
 where
-    T: Hittable,
+    T: Hittable + ?Sized,
 {
-    objects: Vec<Rc<T>>,
+    objects: Vec<Arc<T>>,
+    bbox: Aabb,
+}
+
+impl<T> Default for HittableList<T>
+where
+    T: Hittable + ?Sized,
+{
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+            bbox: Aabb::EMPTY,
+        }
+    }
 }
 
 impl<T> HittableList<T>
 where
-    T: Hittable,
+    T: Hittable + ?Sized,
 {
-    pub fn new(mut self, object: Rc<T>) -> Self {
-        self.add(object);
-        self
+    pub fn new(object: Arc<T>) -> Self {
+        let mut list = Self::default();
+        list.add(object);
+        list
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::EMPTY;
     }
 
-    pub fn add(&mut self, object: Rc<T>) {
+    pub fn add(&mut self, object: Arc<T>) {
+        self.bbox = Aabb::from_boxes(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
+
+    pub fn objects(&self) -> &[Arc<T>] {
+        &self.objects
+    }
 }
 
 impl<T> Hittable for HittableList<T>
 where
-    T: Hittable,
+    T: Hittable + ?Sized,
 {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
         let mut rec = HitRecord::default();
@@ -57,4 +79,37 @@ where
         }
         None
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(r, ray_t);
+            if transmittance <= 0.0 {
+                break;
+            }
+        }
+        transmittance
+    }
+
+    /// Samples each object equally often.
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.objects.iter().map(|object| object.pdf_value(r)).sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let n = self.objects.len();
+        let i = ((random_f64() * n as f64) as usize).min(n - 1);
+        self.objects[i].random(origin, time)
+    }
 }
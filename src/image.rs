@@ -0,0 +1,171 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{color::Color, hdr, pfm, png, ppm};
+
+/// Encodings an `Image` can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// ASCII PPM (P3).
+    Ppm,
+    /// Binary PPM (P6).
+    PpmBinary,
+    Png,
+    /// Radiance RGBE.
+    Hdr,
+    /// Portable Float Map.
+    Pfm,
+}
+
+impl ImageFormat {
+    /// Format names accepted by `from_name`.
+    pub const NAMES: &[&str] = &["p3", "p6", "png", "hdr", "pfm"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "p3" => Some(Self::Ppm),
+            "p6" | "ppm" => Some(Self::PpmBinary),
+            "png" => Some(Self::Png),
+            "hdr" => Some(Self::Hdr),
+            "pfm" => Some(Self::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Picks a format from a file extension. `.ppm` files are written as P6.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Self::PpmBinary),
+            "png" => Some(Self::Png),
+            "hdr" => Some(Self::Hdr),
+            "pfm" => Some(Self::Pfm),
+            _ => None,
+        }
+    }
+}
+
+/// The most pixels a decoder will allocate, enough for a 16384 x 8192
+/// environment map. Larger sizes in a file header are taken as corruption.
+const MAX_PIXELS: u64 = 1 << 27;
+
+/// A rendered frame of linear (not gamma-encoded) colors, stored row by row
+/// from the top-left pixel.
+#[derive(Clone)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); width as usize * height as usize],
+        }
+    }
+
+    /// Like `new`, but `None` for images too large to be worth allocating.
+    pub fn try_new(width: u32, height: u32) -> Option<Self> {
+        (width as u64 * height as u64 <= MAX_PIXELS).then(|| Self::new(width, height))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.pixels[index] = color;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+
+    pub fn row(&self, y: u32) -> &[Color] {
+        let start = self.index(0, y);
+        &self.pixels[start..start + self.width as usize]
+    }
+
+    pub fn row_mut(&mut self, y: u32) -> &mut [Color] {
+        let start = self.index(0, y);
+        let width = self.width as usize;
+        &mut self.pixels[start..start + width]
+    }
+
+    pub fn write(&self, format: ImageFormat, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => ppm::write_ppm(self, out),
+            ImageFormat::PpmBinary => ppm::write_ppm_binary(self, out),
+            ImageFormat::Png => png::write_png(self, out),
+            ImageFormat::Hdr => hdr::write_hdr(self, out),
+            ImageFormat::Pfm => pfm::write_pfm(self, out),
+        }
+    }
+
+    /// Writes the image to `path`, choosing the format from its extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unrecognized image extension: {}", path.display()),
+            )
+        })?;
+        self.save_as(path, format)
+    }
+
+    pub fn save_as(&self, path: impl AsRef<Path>, format: ImageFormat) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(format, &mut out)?;
+        out.flush()
+    }
+
+    /// Decodes an image. P3 and P6 PPMs are told apart by their header, so
+    /// either PPM format reads both.
+    pub fn read(format: ImageFormat, input: &mut impl Read) -> io::Result<Self> {
+        match format {
+            ImageFormat::Ppm | ImageFormat::PpmBinary => ppm::read_ppm(input),
+            ImageFormat::Png => png::read_png(input),
+            ImageFormat::Hdr => hdr::read_hdr(input),
+            ImageFormat::Pfm => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("reading {format:?} images is not supported"),
+            )),
+        }
+    }
+
+    /// Reads the image at `path`, choosing the format from its extension.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unrecognized image extension: {}", path.display()),
+            )
+        })?;
+        Self::read(format, &mut BufReader::new(File::open(path)?))
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
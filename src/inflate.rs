@@ -0,0 +1,340 @@
+//! A decoder for raw deflate streams (RFC 1951), as found inside zlib and PNG.
+//!
+//! Huffman codes are decoded a bit at a time from their canonical code
+//! counts, which keeps the tables tiny at some cost in speed.
+
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits of length symbols 257..=285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits of distance symbols 0..=29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in a dynamic block.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const TOO_LONG: &str = "deflate output is longer than expected";
+
+/// Decompresses a complete raw deflate stream, failing as soon as it would
+/// produce more than `limit` bytes: a few bytes of deflate data can expand a
+/// thousandfold.
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, &'static str> {
+    let mut bits = BitReader::new(data);
+    let mut out = Vec::new();
+
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => stored_block(&mut bits, &mut out, limit)?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                codes_block(&mut bits, &mut out, limit, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                codes_block(&mut bits, &mut out, limit, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type"),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `n` (at most 16) bits, least significant first.
+    fn bits(&mut self, n: u32) -> Result<u32, &'static str> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or("deflate data ends early")?;
+            self.pos += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Discards the bits left in the current byte.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// A canonical Huffman code: how many codes there are of each length, and
+/// the symbols ordered by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, &'static str> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+
+        // Reject codes with more symbols of some length than can exist.
+        // Incomplete codes are allowed, as deflate permits them for a single
+        // distance code.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err("over-subscribed Huffman code");
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, &'static str> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = count as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code")
+    }
+}
+
+fn stored_block(bits: &mut BitReader, out: &mut Vec<u8>, limit: usize) -> Result<(), &'static str> {
+    bits.align();
+    let len = bits.bits(16)?;
+    let nlen = bits.bits(16)?;
+    if len != !nlen & 0xffff {
+        return Err("stored block length does not match its complement");
+    }
+    let block = bits
+        .data
+        .get(bits.pos..bits.pos + len as usize)
+        .ok_or("deflate data ends early")?;
+    if out.len() + block.len() > limit {
+        return Err(TOO_LONG);
+    }
+    out.extend_from_slice(block);
+    bits.pos += len as usize;
+    Ok(())
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    // Both fixed codes are complete, so construction cannot fail.
+    let literals = Huffman::new(&lengths).unwrap();
+    let distances = Huffman::new(&[5; 30]).unwrap();
+    (literals, distances)
+}
+
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err("too many length or distance codes");
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = bits.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_code.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..i]
+                    .last()
+                    .ok_or("repeated code length with no previous length")?;
+                (previous, 3 + bits.bits(2)? as usize)
+            }
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err("code lengths overflow the table");
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    if lengths[256] == 0 {
+        return Err("no end-of-block code");
+    }
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..])?;
+    Ok((literals, distances))
+}
+
+fn codes_block(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    limit: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), &'static str> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 if out.len() >= limit => return Err(TOO_LONG),
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let i = symbol - 257;
+                let len = LENGTH_BASE[i] as usize + bits.bits(LENGTH_EXTRA[i] as u32)? as usize;
+
+                let d = distances.decode(bits)? as usize;
+                if d >= DIST_BASE.len() {
+                    return Err("invalid distance code");
+                }
+                let dist = DIST_BASE[d] as usize + bits.bits(DIST_EXTRA[d] as u32)? as usize;
+                if dist > out.len() {
+                    return Err("distance reaches before the start of the output");
+                }
+                if out.len() + len > limit {
+                    return Err(TOO_LONG);
+                }
+
+                // Copies may overlap their own output, so go a byte at a time.
+                let start = out.len() - dist;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+            _ => return Err("invalid literal/length code"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn stored_block() {
+        let data = [0b001, 5, 0, !5, !0, b'h', b'e', b'l', b'l', b'o'];
+        assert_eq!(inflate(&data, usize::MAX).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn fixed_codes_with_back_references() {
+        let data = hex("cb48cdc9c957c8402701");
+        assert_eq!(
+            inflate(&data, usize::MAX).unwrap(),
+            b"hello hello hello hello"
+        );
+    }
+
+    #[test]
+    fn dynamic_codes() {
+        let data = hex(concat!(
+            "cdcbc70180201005d1567e05d4e2c10640490656b250bd5b86e779b33a8d58fd76",
+            "4225ea01865e1cf57e32a8e984c2f9927360272bb0fe032f92dd3da018755f1c8c",
+            "6f9ad3d401978f9512bf368b0f",
+        ));
+        let expected = [
+            "The quick brown fox jumps over the lazy dog. ".repeat(4),
+            "Pack my box with five dozen liquor jugs.".to_string(),
+        ]
+        .concat();
+        assert_eq!(inflate(&data, usize::MAX).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn malformed_streams_are_errors() {
+        // Reserved block type.
+        assert!(inflate(&[0b111], usize::MAX).is_err());
+        // Stored block whose length check doesn't match.
+        assert!(inflate(&[0b001, 5, 0, 5, 0], usize::MAX).is_err());
+        // Stored block longer than the data.
+        assert!(inflate(&[0b001, 5, 0, !5, !0, b'h'], usize::MAX).is_err());
+        // A fixed-code stream missing its first byte.
+        assert!(inflate(&hex("cb48cdc9c957c8402701")[1..], usize::MAX).is_err());
+        assert!(inflate(&[], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn output_past_the_limit_is_an_error() {
+        // 100,000 zero bytes squeezed into 114.
+        let bomb = [
+            hex("edc13101000000c2a0f54f6d0d0fa0"),
+            vec![0; 96],
+            hex("805703"),
+        ]
+        .concat();
+        assert_eq!(inflate(&bomb, 100_000).unwrap(), vec![0; 100_000]);
+        assert_eq!(inflate(&bomb, 99_999), Err(TOO_LONG));
+        assert_eq!(inflate(&bomb, 1000), Err(TOO_LONG));
+
+        let stored = [0b001, 5, 0, !5, !0, b'h', b'e', b'l', b'l', b'o'];
+        assert_eq!(inflate(&stored, 4), Err(TOO_LONG));
+        assert_eq!(inflate(&hex("cb48cdc9c957c8402701"), 10), Err(TOO_LONG));
+    }
+}
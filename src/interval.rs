@@ -7,25 +7,33 @@ pub struct Interval {
 impl Default for Interval {
     fn default() -> Self {
         Self {
-            min: std::f64::INFINITY,
-            max: std::f64::NEG_INFINITY,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
         }
     }
 }
 
 impl Interval {
     pub const EMPTY: Self = Interval {
-        min: std::f64::INFINITY,
-        max: std::f64::NEG_INFINITY,
+        min: f64::INFINITY,
+        max: f64::NEG_INFINITY,
     };
     pub const UNIVERSE: Self = Interval {
-        min: std::f64::NEG_INFINITY,
-        max: std::f64::INFINITY,
+        min: f64::NEG_INFINITY,
+        max: f64::INFINITY,
     };
     pub const fn new(min: f64, max: f64) -> Self {
         Self { min, max }
     }
 
+    /// The tightest interval enclosing both `a` and `b`.
+    pub fn from_intervals(a: &Interval, b: &Interval) -> Self {
+        Self {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
     pub fn size(&self) -> f64 {
         self.max - self.min
     }
@@ -48,4 +56,9 @@ impl Interval {
 
         x
     }
+
+    pub fn expand(&self, delta: f64) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
 }
@@ -0,0 +1,68 @@
+//! A CPU path tracer following the "Ray Tracing in One Weekend" series.
+//!
+//! Build a world out of [`Hittable`]s and [`Material`]s (or load one with
+//! [`Scene::load`]), configure a [`Camera`] through [`CameraBuilder`], and
+//! encode the rendered [`Image`] with one of the writers in [`ppm`], [`png`],
+//! [`hdr`] or [`pfm`].
+
+pub mod aabb;
+pub mod background;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod constant_medium;
+pub mod environment;
+pub mod grid_medium;
+pub mod hdr;
+pub mod hittable;
+pub mod hittable_list;
+pub mod image;
+mod inflate;
+pub mod interval;
+pub mod material;
+pub mod obj;
+pub mod onb;
+pub mod pdf;
+pub mod perlin;
+pub mod pfm;
+pub mod ply;
+pub mod png;
+pub mod ppm;
+pub mod quad;
+pub mod ray;
+pub mod rtweekend;
+pub mod scene;
+pub mod scenes;
+pub mod sphere;
+pub mod texture;
+mod toml;
+pub mod transform;
+pub mod triangle;
+pub mod vec3;
+pub mod voxel_grid;
+
+pub use background::{Background, Environment};
+pub use bvh::BvhNode;
+pub use camera::{Camera, CameraBuilder};
+pub use color::Color;
+pub use constant_medium::ConstantMedium;
+pub use environment::EnvironmentMap;
+pub use grid_medium::GridMedium;
+pub use hittable::{HitRecord, Hittable};
+pub use hittable_list::HittableList;
+pub use image::{Image, ImageFormat};
+pub use material::{
+    Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal, Scatter, ScatterRecord,
+};
+pub use pdf::{CosinePdf, MixturePdf, Pdf, SpherePdf};
+pub use quad::{Quad, make_box};
+pub use ray::Ray;
+pub use scene::{Scene, SceneError};
+pub use sphere::Sphere;
+pub use texture::{
+    CheckerTexture, ImageTexture, MarbleTexture, NoiseTexture, SolidColor, Texture, WoodTexture,
+};
+pub use transform::{AnimatedTransform, Matrix4, Pose, Transform};
+pub use triangle::{MeshError, MeshFace, Triangle, TriangleMesh};
+pub use vec3::{Point3, Vec3};
+pub use voxel_grid::VoxelGrid;
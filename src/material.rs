@@ -1,63 +1,114 @@
+use std::{f64::consts::PI, sync::Arc};
+
 use crate::{
     color::Color,
     hittable::HitRecord,
+    pdf::{CosinePdf, Pdf, SpherePdf},
     ray::Ray,
     rtweekend::random_f64,
+    texture::{SolidColor, Texture},
     vec3::{dot, random_unit_vector, reflect, refract, unit_vector},
 };
 
-pub trait Material {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+/// How a material continues a path from a hit.
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub scatter: Scatter,
+}
+
+pub enum Scatter {
+    /// A ray picked by the material itself, as mirrors and glass do, whose
+    /// light is scaled by `attenuation` alone.
+    Specular(Ray),
+    /// Directions to be drawn from a distribution. The light along one is
+    /// scaled by `attenuation` times the material's `scattering_pdf`, divided
+    /// by the density with which it was drawn, so any other distribution,
+    /// such as one aimed at the lights, may be used in its place.
+    Pdf(Arc<dyn Pdf>),
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<ScatterRecord> {
         None
     }
+
+    /// How much of the light scattered toward `scattered` comes from each
+    /// direction, as a density over solid angle. Only used for materials
+    /// that scatter through a [`Scatter::Pdf`].
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// Light given off at the hit point, added to whatever it scatters.
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::default()
+    }
+
+    /// Whether `emitted` gives off any light, making objects of this
+    /// material worth sampling as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: &Color) -> Self {
-        Self { albedo: *albedo }
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let mut scatter_direction = rec.normal + random_unit_vector();
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            attenuation: self.tex.value(rec.u, rec.v, &rec.p),
+            scatter: Scatter::Pdf(Arc::new(CosinePdf::new(&rec.normal))),
+        })
+    }
 
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-        Some((self.albedo, Ray::new(&rec.p, &scatter_direction)))
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = dot(&rec.normal, &unit_vector(scattered.direction()));
+        (cos_theta / PI).max(0.0)
     }
 }
 
 pub struct Metal {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
     fuzz: f64,
 }
 
 impl Metal {
     pub fn new(albedo: &Color, fuzz: f64) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>, fuzz: f64) -> Self {
         Self {
-            albedo: *albedo,
+            tex,
             fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         let mut reflected = reflect(r_in.direction(), &rec.normal);
         reflected = unit_vector(&reflected) + (self.fuzz * random_unit_vector());
-        let scattered = Ray::new(&rec.p, &reflected);
-        let attenuation = self.albedo;
-        if dot(scattered.direction(), &rec.normal) > 0.0 {
-            Some((attenuation, scattered))
-        } else {
-            None
+        let scattered = Ray::with_time(&rec.p, &reflected, r_in.time());
+        if dot(scattered.direction(), &rec.normal) <= 0.0 {
+            return None;
         }
+        Some(ScatterRecord {
+            attenuation: self.tex.value(rec.u, rec.v, &rec.p),
+            scatter: Scatter::Specular(scattered),
+        })
     }
 }
 
@@ -78,15 +129,14 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         let ri = if rec.front_face {
             1.0 / self.refraction_index
         } else {
             self.refraction_index
         };
 
-        let unit_direction = unit_vector(&r_in.direction());
+        let unit_direction = unit_vector(r_in.direction());
         let cos_theta = dot(&-unit_direction, &rec.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
@@ -96,7 +146,156 @@ impl Material for Dielectric {
         } else {
             refract(&unit_direction, &rec.normal, ri)
         };
-        let scattered = Ray::new(&rec.p, &direction);
-        Some((attenuation, scattered))
+        let scattered = Ray::with_time(&rec.p, &direction, r_in.time());
+        Some(ScatterRecord {
+            attenuation: Color::new(1.0, 1.0, 1.0),
+            scatter: Scatter::Specular(scattered),
+        })
+    }
+}
+
+/// A light source: emits its texture's color and scatters nothing.
+pub struct DiffuseLight {
+    tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: &Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+/// The phase function of a participating medium: scatters equally in every
+/// direction, tinted by the texture.
+pub struct Isotropic {
+    tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: &Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            attenuation: self.tex.value(rec.u, rec.v, &rec.p),
+            scatter: Scatter::Pdf(Arc::new(SpherePdf)),
+        })
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        texture::CheckerTexture,
+        vec3::{Point3, Vec3},
+    };
+
+    fn rec_at(p: Point3) -> HitRecord {
+        HitRecord {
+            p,
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            front_face: true,
+            u: 0.25,
+            v: 0.75,
+            ..HitRecord::default()
+        }
+    }
+
+    #[test]
+    fn lights_emit_their_texture_and_scatter_nothing() {
+        let light = DiffuseLight::from_texture(Arc::new(CheckerTexture::from_colors(
+            1.0,
+            &Color::new(4.0, 4.0, 4.0),
+            &Color::new(0.0, 1.0, 0.0),
+        )));
+        let r = Ray::new(&Point3::new(0.5, 2.0, 0.5), &Vec3::new(0.0, -1.0, 0.0));
+        let even = rec_at(Point3::new(0.5, 0.0, 0.5));
+        let odd = rec_at(Point3::new(1.5, 0.0, 0.5));
+
+        assert!(light.is_emissive());
+        assert_eq!(light.emitted(&even).0.e, [4.0; 3]);
+        assert_eq!(light.emitted(&odd).0.e, [0.0, 1.0, 0.0]);
+        assert!(light.scatter(&r, &even).is_none());
+    }
+
+    #[test]
+    fn other_materials_emit_nothing() {
+        let rec = rec_at(Point3::new(0.0, 0.0, 0.0));
+        let materials: [Arc<dyn Material>; 4] = [
+            Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5))),
+            Arc::new(Metal::new(&Color::new(0.5, 0.5, 0.5), 0.1)),
+            Arc::new(Dielectric::new(1.5)),
+            Arc::new(Isotropic::new(&Color::new(0.5, 0.5, 0.5))),
+        ];
+        for mat in materials {
+            assert!(!mat.is_emissive());
+            assert_eq!(mat.emitted(&rec).0.e, [0.0; 3]);
+        }
+    }
+
+    #[test]
+    fn diffuse_pdfs_match_their_scattering_pdf() {
+        // The camera weights each path by scattering_pdf / pdf, which is
+        // exactly one when a material samples its own distribution.
+        let rec = rec_at(Point3::new(0.0, 0.0, 0.0));
+        let r_in = Ray::new(&Point3::new(0.0, 1.0, 1.0), &Vec3::new(0.0, -1.0, -1.0));
+        let materials: [Arc<dyn Material>; 2] = [
+            Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5))),
+            Arc::new(Isotropic::new(&Color::new(0.5, 0.5, 0.5))),
+        ];
+        for mat in materials {
+            let Some(Scatter::Pdf(pdf)) = mat.scatter(&r_in, &rec).map(|s| s.scatter) else {
+                panic!("diffuse materials scatter by a pdf");
+            };
+            for _ in 0..100 {
+                let d = pdf.generate();
+                let scattered = Ray::new(&rec.p, &d);
+                let weight = mat.scattering_pdf(&r_in, &rec, &scattered);
+                assert!((weight - pdf.value(&d)).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn mirrors_and_glass_scatter_specularly() {
+        let rec = rec_at(Point3::new(0.0, 0.0, 0.0));
+        let r_in = Ray::new(&Point3::new(0.0, 1.0, 1.0), &Vec3::new(0.0, -1.0, -1.0));
+        let mirror = Metal::new(&Color::new(0.8, 0.8, 0.8), 0.0);
+        let Some(Scatter::Specular(reflected)) = mirror.scatter(&r_in, &rec).map(|s| s.scatter)
+        else {
+            panic!("metal scatters specularly");
+        };
+        let d = unit_vector(reflected.direction());
+        assert!((d.y() - 0.5f64.sqrt()).abs() < 1e-12 && (d.z() + 0.5f64.sqrt()).abs() < 1e-12);
+        assert!(matches!(
+            Dielectric::new(1.5).scatter(&r_in, &rec).map(|s| s.scatter),
+            Some(Scatter::Specular(_))
+        ));
     }
 }
@@ -0,0 +1,555 @@
+//! Wavefront OBJ import, with materials from companion MTL files.
+//!
+//! Supports `v`, `vt`, `vn`, `f` (polygons are fan-triangulated, negative
+//! indices count back from the latest element), `g`/`o` groups, `usemtl` and
+//! `mtllib`. Other statements such as `s`, `l` or free-form curves are skipped.
+//! Each group/material pair becomes one `TriangleMesh`.
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    color::Color,
+    hittable_list::HittableList,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    triangle::{MeshFace, TriangleMesh},
+    vec3::{Point3, Vec3},
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Loads every face of an OBJ file. Faces without a `usemtl` get a light gray
+/// `Lambertian`.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<HittableList, ObjError> {
+    load_obj_with_material(path, None)
+}
+
+/// Like `load_obj`, but `material`, when given, replaces whatever the MTL files say.
+pub fn load_obj_with_material(
+    path: impl AsRef<Path>,
+    material: Option<Arc<dyn Material>>,
+) -> Result<HittableList, ObjError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    let err = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let default_material: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(0.8, 0.8, 0.8)));
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut uvs: Vec<(f64, f64)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+
+    let mut group = String::new();
+    let mut current_material: Option<String> = None;
+    // Meshes in order of first use, keyed by (group, material).
+    let mut meshes: Vec<((String, Option<String>), MeshBuilder)> = Vec::new();
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut words = raw_line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = numbers::<3>(&args, 3..=4).map_err(|m| err(line, m))?;
+                positions.push(Point3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = numbers::<2>(&args, 1..=3).map_err(|m| err(line, m))?;
+                uvs.push((u, v));
+            }
+            "vn" => {
+                let [x, y, z] = numbers::<3>(&args, 3..=3).map_err(|m| err(line, m))?;
+                normals.push(Vec3::new(x, y, z));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(err(line, "a face needs at least three vertices".into()));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| {
+                        parse_corner(arg, positions.len(), uvs.len(), normals.len())
+                            .map_err(|m| err(line, m))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let key = (group.clone(), current_material.clone());
+                let mesh = match meshes.iter().position(|(k, _)| *k == key) {
+                    Some(i) => &mut meshes[i].1,
+                    None => {
+                        meshes.push((key, MeshBuilder::default()));
+                        &mut meshes.last_mut().unwrap().1
+                    }
+                };
+                for i in 1..corners.len() - 1 {
+                    mesh.add_face(
+                        [corners[0], corners[i], corners[i + 1]],
+                        &positions,
+                        &uvs,
+                        &normals,
+                    );
+                }
+            }
+            "g" | "o" => group = args.join(" "),
+            "usemtl" => {
+                let name = args.join(" ");
+                if material.is_none() && !materials.contains_key(&name) {
+                    return Err(err(line, format!("material `{name}` is not defined")));
+                }
+                current_material = Some(name);
+            }
+            "mtllib" if material.is_none() => {
+                for file in &args {
+                    materials.extend(load_mtl(&base.join(file))?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut list: HittableList = HittableList::default();
+    for ((_, material_name), mesh) in meshes {
+        let mat = match (&material, material_name) {
+            (Some(mat), _) => mat.clone(),
+            (None, Some(name)) => materials[&name].clone(),
+            (None, None) => default_material.clone(),
+        };
+        list.add(Arc::new(mesh.build(Some(mat))));
+    }
+    Ok(list)
+}
+
+/// Parses an MTL file into project materials.
+///
+/// Materials with a nonzero emission `Ke` become `DiffuseLight`. Transparent
+/// materials (`d` < 1 or a refraction illumination model) become
+/// `Dielectric` with index `Ni`; reflective ones (illum 3, 5, 8, or only a
+/// specular color) become `Metal` tinted by `Ks` with fuzz derived from `Ns`;
+/// everything else is `Lambertian` with albedo `Kd`.
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let source = read(path)?;
+    let err = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlParams)> = None;
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut words = raw_line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, params)) = current.take() {
+                materials.insert(name, params.to_material());
+            }
+            if args.is_empty() {
+                return Err(err(line, "`newmtl` needs a name".into()));
+            }
+            current = Some((args.join(" "), MtlParams::default()));
+            continue;
+        }
+
+        let Some((_, params)) = current.as_mut() else {
+            return Err(err(line, format!("`{keyword}` before any `newmtl`")));
+        };
+        match keyword {
+            "Kd" => params.kd = color(&args).map_err(|m| err(line, m))?,
+            "Ks" => params.ks = color(&args).map_err(|m| err(line, m))?,
+            "Ke" => params.ke = color(&args).map_err(|m| err(line, m))?,
+            "Ns" => params.ns = numbers::<1>(&args, 1..=1).map_err(|m| err(line, m))?[0],
+            "Ni" => params.ni = numbers::<1>(&args, 1..=1).map_err(|m| err(line, m))?[0],
+            "d" => params.d = numbers::<1>(&args, 1..=1).map_err(|m| err(line, m))?[0],
+            "Tr" => params.d = 1.0 - numbers::<1>(&args, 1..=1).map_err(|m| err(line, m))?[0],
+            "illum" => {
+                let [illum] = numbers::<1>(&args, 1..=1).map_err(|m| err(line, m))?;
+                if illum.fract() != 0.0 || !(0.0..=10.0).contains(&illum) {
+                    return Err(err(line, format!("invalid illumination model `{illum}`")));
+                }
+                params.illum = illum as u32;
+            }
+            // Ka, texture maps and vendor extensions have no counterpart here.
+            _ => {}
+        }
+    }
+    if let Some((name, params)) = current {
+        materials.insert(name, params.to_material());
+    }
+    Ok(materials)
+}
+
+struct MtlParams {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ns: f64,
+    ni: f64,
+    d: f64,
+    illum: u32,
+}
+
+impl Default for MtlParams {
+    fn default() -> Self {
+        Self {
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::default(),
+            ke: Color::default(),
+            ns: 0.0,
+            ni: 1.5,
+            d: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlParams {
+    fn to_material(&self) -> Arc<dyn Material> {
+        let transparent = self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9);
+        let reflective = matches!(self.illum, 3 | 5 | 8)
+            || (self.kd.0.length_squared() == 0.0 && self.ks.0.length_squared() > 0.0);
+
+        if self.ke.0.length_squared() > 0.0 {
+            Arc::new(DiffuseLight::new(&self.ke))
+        } else if transparent {
+            Arc::new(Dielectric::new(self.ni))
+        } else if reflective {
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt();
+            Arc::new(Metal::new(&self.ks, fuzz))
+        } else {
+            Arc::new(Lambertian::new(&self.kd))
+        }
+    }
+}
+
+/// Collects the faces of one group/material, renumbering the vertices it uses.
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<Point3>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+    faces: Vec<MeshFace>,
+    position_remap: HashMap<usize, usize>,
+    uv_remap: HashMap<usize, usize>,
+    normal_remap: HashMap<usize, usize>,
+}
+
+/// Zero-based position, texture and normal indices of one face corner.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl MeshBuilder {
+    fn add_face(
+        &mut self,
+        corners: [Corner; 3],
+        positions: &[Point3],
+        uvs: &[(f64, f64)],
+        normals: &[Vec3],
+    ) {
+        let vertices = corners.map(|(v, _, _)| {
+            *self.position_remap.entry(v).or_insert_with(|| {
+                self.positions.push(positions[v]);
+                self.positions.len() - 1
+            })
+        });
+
+        let face_uvs = if corners.iter().all(|(_, vt, _)| vt.is_some()) {
+            Some(corners.map(|(_, vt, _)| {
+                let vt = vt.unwrap();
+                *self.uv_remap.entry(vt).or_insert_with(|| {
+                    self.uvs.push(uvs[vt]);
+                    self.uvs.len() - 1
+                })
+            }))
+        } else {
+            None
+        };
+
+        let face_normals = if corners.iter().all(|(_, _, vn)| vn.is_some()) {
+            Some(corners.map(|(_, _, vn)| {
+                let vn = vn.unwrap();
+                *self.normal_remap.entry(vn).or_insert_with(|| {
+                    self.normals.push(normals[vn]);
+                    self.normals.len() - 1
+                })
+            }))
+        } else {
+            None
+        };
+
+        self.faces.push(MeshFace {
+            vertices,
+            normals: face_normals,
+            uvs: face_uvs,
+        });
+    }
+
+    fn build(self, mat: Option<Arc<dyn Material>>) -> TriangleMesh {
+        TriangleMesh::new(self.positions, self.normals, self.uvs, self.faces, mat)
+            .expect("faces only index vertices added by `add_face`")
+    }
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Parses the first `N` of `args`, requiring the count to be in `allowed`.
+fn numbers<const N: usize>(
+    args: &[&str],
+    allowed: std::ops::RangeInclusive<usize>,
+) -> Result<[f64; N], String> {
+    if !allowed.contains(&args.len()) {
+        return Err(format!(
+            "expected {} numbers, found {}",
+            if allowed.start() == allowed.end() {
+                allowed.start().to_string()
+            } else {
+                format!("{} to {}", allowed.start(), allowed.end())
+            },
+            args.len()
+        ));
+    }
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg
+            .parse()
+            .map_err(|_| format!("`{arg}` is not a number"))?;
+    }
+    Ok(values)
+}
+
+fn color(args: &[&str]) -> Result<Color, String> {
+    if args.first() == Some(&"spectral") || args.first() == Some(&"xyz") {
+        return Err("only RGB colors are supported".into());
+    }
+    // A single value means a gray.
+    let [r, g, b] = match args.len() {
+        1 => {
+            let [v] = numbers::<1>(args, 1..=1)?;
+            [v, v, v]
+        }
+        _ => numbers::<3>(args, 3..=3)?,
+    };
+    Ok(Color::new(r, g, b))
+}
+
+/// Parses a face corner such as `3`, `3/1`, `3//2` or `-1/-1/-1`.
+fn parse_corner(
+    corner: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let index = |part: Option<&str>, count: usize, what: &str| -> Result<Option<usize>, String> {
+        match part {
+            None | Some("") => Ok(None),
+            Some(s) => {
+                let i: i64 = s
+                    .parse()
+                    .map_err(|_| format!("invalid {what} index `{s}` in `{corner}`"))?;
+                let resolved = if i < 0 { count as i64 + i } else { i - 1 };
+                if i == 0 || resolved < 0 || resolved >= count as i64 {
+                    return Err(format!(
+                        "{what} index {i} is out of range (there are {count} so far)"
+                    ));
+                }
+                Ok(Some(resolved as usize))
+            }
+        }
+    };
+
+    let v = index(parts.next(), position_count, "vertex")?
+        .ok_or_else(|| format!("face corner `{corner}` has no vertex index"))?;
+    let vt = index(parts.next(), uv_count, "texture")?;
+    let vn = index(parts.next(), normal_count, "normal")?;
+    if parts.next().is_some() {
+        return Err(format!("malformed face corner `{corner}`"));
+    }
+    Ok((v, vt, vn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::Hittable, interval::Interval, ray::Ray};
+
+    /// Writes the given files into a fresh directory in the temp directory.
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("obj-test-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn hits_at(list: &HittableList, x: f64, y: f64) -> bool {
+        let r = Ray::new(&Point3::new(x, y, 1.0), &Vec3::new(0.0, 0.0, -1.0));
+        list.hit(&r, Interval::new(0.0, f64::INFINITY)).is_some()
+    }
+
+    fn parse_error(err: ObjError) -> (PathBuf, usize, String) {
+        match err {
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => (path, line, message),
+            ObjError::Io { path, error } => panic!("{}: {error}", path.display()),
+        }
+    }
+
+    #[test]
+    fn polygons_are_fanned_from_their_first_corner() {
+        // A concave quad whose first corner is the reflex one: only a fan
+        // from there stays inside the outline.
+        let dir = temp_dir(
+            "fan",
+            &[(
+                "dart.obj",
+                "v 0.5 1 0\nv 0 0 0\nv 2 1 0\nv 0 2 0\nf 1 2 3 4\n",
+            )],
+        );
+        let list = load_obj(dir.join("dart.obj")).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(list.objects().len(), 1);
+        assert!(hits_at(&list, 1.0, 0.9));
+        assert!(hits_at(&list, 1.0, 1.2));
+        assert!(!hits_at(&list, 0.25, 1.0));
+        assert!(!hits_at(&list, 1.5, 0.2));
+    }
+
+    #[test]
+    fn groups_and_materials_split_meshes() {
+        let dir = temp_dir(
+            "groups",
+            &[
+                (
+                    "scene.mtl",
+                    "# materials\nnewmtl glow\nKe 2 2 2\nnewmtl glass\nd 0.5\nNi 1.3\n",
+                ),
+                (
+                    "scene.obj",
+                    "mtllib scene.mtl\n\
+                     v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 1\n\
+                     f 1/1/1 2/2/1 3/3/1\n\
+                     usemtl glow\nf -4//-1 -3//-1 -1//-1\n\
+                     g lid\nf 1 3 4\nusemtl glass\nf 2 3 4\n",
+                ),
+            ],
+        );
+        let list = load_obj(dir.join("scene.obj")).unwrap();
+        let materials = load_mtl(&dir.join("scene.mtl")).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        // (default, none), (default, glow), (lid, glow), (lid, glass)
+        assert_eq!(list.objects().len(), 4);
+        assert!(materials["glow"].is_emissive());
+        assert!(!materials["glass"].is_emissive());
+    }
+
+    #[test]
+    fn errors_name_the_file_and_line() {
+        let dir = temp_dir(
+            "errors",
+            &[
+                ("range.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4\n"),
+                ("number.obj", "v 0 0 0\nv 1 zero 0\n"),
+                ("short.obj", "v 0 0 0\nv 1 0 0\nf 1 2\n"),
+                ("usemtl.obj", "usemtl nowhere\n"),
+                ("bad.mtl", "newmtl a\nKd 1 1\n"),
+                ("mtllib.obj", "# uses a broken library\nmtllib bad.mtl\n"),
+            ],
+        );
+        let error = |file: &str| parse_error(load_obj(dir.join(file)).err().unwrap());
+
+        let (path, line, message) = error("range.obj");
+        assert_eq!((path, line), (dir.join("range.obj"), 5));
+        assert_eq!(
+            message,
+            "vertex index 4 is out of range (there are 3 so far)"
+        );
+        assert_eq!(error("number.obj").1, 2);
+        assert_eq!(error("short.obj").1, 3);
+        assert_eq!(error("usemtl.obj").2, "material `nowhere` is not defined");
+
+        // Mistakes in a material library point into the library.
+        let (path, line, message) = error("mtllib.obj");
+        assert_eq!((path, line), (dir.join("bad.mtl"), 2));
+        assert_eq!(message, "expected 3 numbers, found 2");
+
+        let err = load_obj(dir.join("range.obj")).err().unwrap();
+        assert!(
+            err.to_string()
+                .ends_with("range.obj:5: vertex index 4 is out of range (there are 3 so far)")
+        );
+        assert!(matches!(
+            load_obj(dir.join("missing.obj")),
+            Err(ObjError::Io { .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn face_corners() {
+        assert_eq!(parse_corner("3", 3, 0, 0), Ok((2, None, None)));
+        assert_eq!(parse_corner("1/2", 3, 2, 0), Ok((0, Some(1), None)));
+        assert_eq!(parse_corner("1//1", 3, 0, 1), Ok((0, None, Some(0))));
+        assert_eq!(parse_corner("-1/-2/-1", 3, 2, 4), Ok((2, Some(0), Some(3))));
+        assert!(parse_corner("0", 3, 0, 0).is_err());
+        assert!(parse_corner("-4", 3, 0, 0).is_err());
+        assert!(parse_corner("/1", 3, 1, 0).is_err());
+        assert!(parse_corner("1/1/1/1", 3, 1, 1).is_err());
+        assert!(parse_corner("a", 3, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    onb::Onb,
+    rtweekend::random_f64,
+    vec3::{Vec3, dot, random_cosine_direction, random_unit_vector, unit_vector},
+};
+
+/// A distribution of directions to continue a path in: it can pick one, and
+/// tell the density over solid angle with which it picks any other.
+pub trait Pdf: Send + Sync {
+    fn value(&self, direction: &Vec3) -> f64;
+
+    fn generate(&self) -> Vec3;
+}
+
+/// Every direction equally likely.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self) -> Vec3 {
+        random_unit_vector()
+    }
+}
+
+/// Directions about a surface normal, more likely the closer they are to it,
+/// as a diffuse surface reflects light.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: &Vec3) -> Self {
+        Self { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine_theta = dot(&unit_vector(direction), self.uvw.w());
+        (cosine_theta / PI).max(0.0)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.transform(&random_cosine_direction())
+    }
+}
+
+/// An even mix of two distributions: each direction is drawn from one of
+/// them, picked by a coin toss.
+pub struct MixturePdf {
+    p: [Arc<dyn Pdf>; 2],
+}
+
+impl MixturePdf {
+    pub fn new(p0: Arc<dyn Pdf>, p1: Arc<dyn Pdf>) -> Self {
+        Self { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        if random_f64() < 0.5 {
+            self.p[0].generate()
+        } else {
+            self.p[1].generate()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: usize = 100_000;
+
+    /// Estimates the solid angle a distribution covers, E[1 / value], from
+    /// its own samples; it is only right if `generate` agrees with `value`.
+    fn covered(pdf: &dyn Pdf) -> f64 {
+        (0..SAMPLES)
+            .map(|_| 1.0 / pdf.value(&pdf.generate()))
+            .sum::<f64>()
+            / SAMPLES as f64
+    }
+
+    /// Estimates the integral of `value` over the sphere of directions.
+    fn total(pdf: &dyn Pdf) -> f64 {
+        let sum = (0..SAMPLES)
+            .map(|_| pdf.value(&random_unit_vector()))
+            .sum::<f64>();
+        4.0 * PI * sum / SAMPLES as f64
+    }
+
+    #[test]
+    fn densities_integrate_to_one() {
+        let normal = Vec3::new(1.0, 2.0, -2.0);
+        let pdfs: [(&str, Arc<dyn Pdf>); 3] = [
+            ("sphere", Arc::new(SpherePdf)),
+            ("cosine", Arc::new(CosinePdf::new(&normal))),
+            (
+                "mixture",
+                Arc::new(MixturePdf::new(
+                    Arc::new(SpherePdf),
+                    Arc::new(CosinePdf::new(&normal)),
+                )),
+            ),
+        ];
+        for (name, pdf) in pdfs {
+            let total = total(pdf.as_ref());
+            assert!((total - 1.0).abs() < 0.02, "{name}: {total}");
+        }
+    }
+
+    #[test]
+    fn samples_follow_the_density() {
+        let normal = Vec3::new(0.0, 0.0, 3.0);
+        let cosine = CosinePdf::new(&normal);
+        for _ in 0..1000 {
+            let d = cosine.generate();
+            assert!((d.length() - 1.0).abs() < 1e-9);
+            assert!(d.z() >= 0.0, "{d:?} points away from the normal");
+        }
+        // Half the sphere for the cosine, all of it for the sphere and for
+        // a mixture that includes it.
+        let hemisphere = covered(&cosine);
+        assert!((hemisphere - 2.0 * PI).abs() < 0.1, "{hemisphere}");
+        assert!((covered(&SpherePdf) - 4.0 * PI).abs() < 1e-9);
+        let mixture = covered(&MixturePdf::new(Arc::new(SpherePdf), Arc::new(cosine)));
+        assert!((mixture - 4.0 * PI).abs() < 0.1, "{mixture}");
+
+        // The mean cosine to the normal under a cosine density is 2/3.
+        let cosine = CosinePdf::new(&normal);
+        let mean = (0..SAMPLES).map(|_| cosine.generate().z()).sum::<f64>() / SAMPLES as f64;
+        assert!((mean - 2.0 / 3.0).abs() < 0.01, "{mean}");
+    }
+
+    #[test]
+    fn mixtures_average_their_parts() {
+        let cosine = CosinePdf::new(&Vec3::new(0.0, 1.0, 0.0));
+        let mixture = MixturePdf::new(
+            Arc::new(SpherePdf),
+            Arc::new(CosinePdf::new(&Vec3::new(0.0, 1.0, 0.0))),
+        );
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert_eq!(cosine.value(&up), 1.0 / PI);
+        assert_eq!(cosine.value(&down), 0.0);
+        assert!((mixture.value(&up) - (0.5 / (4.0 * PI) + 0.5 / PI)).abs() < 1e-15);
+        assert!((mixture.value(&down) - 0.5 / (4.0 * PI)).abs() < 1e-15);
+    }
+}
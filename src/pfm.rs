@@ -0,0 +1,25 @@
+use std::io::{self, Write};
+
+use crate::image::Image;
+
+/// Writes `image` as a color Portable Float Map with unclamped linear values.
+///
+/// PFM stores scanlines bottom-to-top; the negative scale in the header marks
+/// the samples as little-endian.
+pub fn write_pfm(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "PF")?;
+    writeln!(out, "{} {}", image.width(), image.height())?;
+    writeln!(out, "-1.0")?;
+
+    let mut scanline = Vec::with_capacity(12 * image.width() as usize);
+    for y in (0..image.height()).rev() {
+        scanline.clear();
+        for pixel_color in image.row(y) {
+            for component in pixel_color.0.e {
+                scanline.extend_from_slice(&(component as f32).to_le_bytes());
+            }
+        }
+        out.write_all(&scanline)?;
+    }
+    Ok(())
+}
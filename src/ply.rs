@@ -0,0 +1,716 @@
+//! Stanford PLY import, in ASCII or binary little-endian form.
+//!
+//! A file with a `face` element becomes a `TriangleMesh` (polygons are
+//! fan-triangulated; `nx`/`ny`/`nz` and `u`/`v` or `s`/`t` vertex properties
+//! are used when present). A file with only vertices is treated as a point
+//! cloud and becomes small spheres, colored by `red`/`green`/`blue` when the
+//! vertices carry them.
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    aabb::Aabb,
+    bvh::BvhNode,
+    color::{Color, srgb_to_linear},
+    hittable::Hittable,
+    hittable_list::HittableList,
+    material::{Lambertian, Material},
+    sphere::Sphere,
+    triangle::{MeshFace, TriangleMesh},
+    vec3::{Point3, Vec3},
+};
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        /// Line of the header or ASCII body; `None` inside binary data.
+        line: Option<usize>,
+        message: String,
+    },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            PlyError::Parse {
+                path,
+                line: Some(line),
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            PlyError::Parse {
+                path,
+                line: None,
+                message,
+            } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+#[derive(Default)]
+pub struct PlyOptions {
+    /// Radius of the spheres a point cloud becomes. Defaults to 0.5% of the
+    /// cloud's bounding box diagonal.
+    pub point_radius: Option<f64>,
+    /// Overrides the gray default of meshes and the per-vertex colors of points.
+    pub material: Option<Arc<dyn Material>>,
+}
+
+pub fn load_ply(path: impl AsRef<Path>) -> Result<HittableList, PlyError> {
+    load_ply_with(path, &PlyOptions::default())
+}
+
+pub fn load_ply_with(
+    path: impl AsRef<Path>,
+    options: &PlyOptions,
+) -> Result<HittableList, PlyError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|error| PlyError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let err = |line: Option<usize>, message: String| PlyError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let header = parse_header(&bytes).map_err(|(line, m)| err(Some(line), m))?;
+    let body = &bytes[header.body_offset..];
+    let mut reader: Box<dyn BodyReader> = match header.format {
+        Format::Ascii => Box::new(AsciiReader::new(body, header.lines + 1)),
+        Format::BinaryLittleEndian => Box::new(BinaryReader { data: body, pos: 0 }),
+    };
+
+    let mut vertices = None;
+    let mut faces = None;
+    for element in &header.elements {
+        let rows = read_element(reader.as_mut(), element).map_err(|(line, m)| err(line, m))?;
+        match element.name.as_str() {
+            "vertex" => vertices = Some((element, rows)),
+            "face" => faces = Some((element, rows)),
+            _ => {}
+        }
+    }
+
+    let Some((vertex_element, vertex_rows)) = vertices else {
+        return Err(err(None, "no `vertex` element".into()));
+    };
+    let column = |name: &str| {
+        vertex_element
+            .properties
+            .iter()
+            .position(|p| p.name == name)
+    };
+    let (Some(x), Some(y), Some(z)) = (column("x"), column("y"), column("z")) else {
+        return Err(err(
+            None,
+            "vertices need `x`, `y` and `z` properties".into(),
+        ));
+    };
+    let positions: Vec<Point3> = vertex_rows
+        .iter()
+        .map(|row| {
+            let row = &row.values;
+            Point3::new(row[x].scalar(), row[y].scalar(), row[z].scalar())
+        })
+        .collect();
+
+    let mut list: HittableList = HittableList::default();
+    match faces {
+        Some((face_element, face_rows)) => {
+            let Some(indices) = face_element
+                .properties
+                .iter()
+                .position(|p| p.name == "vertex_indices" || p.name == "vertex_index")
+            else {
+                return Err(err(None, "faces need a `vertex_indices` list".into()));
+            };
+
+            let normals: Vec<Vec3> = match (column("nx"), column("ny"), column("nz")) {
+                (Some(nx), Some(ny), Some(nz)) => vertex_rows
+                    .iter()
+                    .map(|row| {
+                        let row = &row.values;
+                        Vec3::new(row[nx].scalar(), row[ny].scalar(), row[nz].scalar())
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let uv_columns = match (column("u"), column("v")) {
+                (Some(u), Some(v)) => Some((u, v)),
+                _ => column("s").zip(column("t")),
+            };
+            let uvs: Vec<(f64, f64)> = match uv_columns {
+                Some((u, v)) => vertex_rows
+                    .iter()
+                    .map(|row| (row.values[u].scalar(), row.values[v].scalar()))
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            let mut mesh_faces = Vec::new();
+            for (i, row) in face_rows.iter().enumerate() {
+                let polygon = row.values[indices].list();
+                if polygon.len() < 3 {
+                    return Err(err(
+                        row.line,
+                        format!("face {i} has fewer than three vertices"),
+                    ));
+                }
+                let polygon = polygon
+                    .iter()
+                    .map(|&k| {
+                        if k < 0.0 || k as usize >= positions.len() {
+                            Err(err(
+                                row.line,
+                                format!("face {i} refers to missing vertex {k}"),
+                            ))
+                        } else {
+                            Ok(k as usize)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                for k in 1..polygon.len() - 1 {
+                    let corner = [polygon[0], polygon[k], polygon[k + 1]];
+                    mesh_faces.push(MeshFace {
+                        vertices: corner,
+                        normals: (!normals.is_empty()).then_some(corner),
+                        uvs: (!uvs.is_empty()).then_some(corner),
+                    });
+                }
+            }
+
+            let mat = options
+                .material
+                .clone()
+                .unwrap_or_else(|| Arc::new(Lambertian::new(&Color::new(0.8, 0.8, 0.8))));
+            let mesh = TriangleMesh::new(positions, normals, uvs, mesh_faces, Some(mat))
+                .map_err(|e| err(None, e.to_string()))?;
+            list.add(Arc::new(mesh));
+        }
+        None => {
+            let radius = options.point_radius.unwrap_or_else(|| {
+                let bounds = positions.iter().fold(Aabb::EMPTY, |b, p| {
+                    Aabb::from_boxes(&b, &Aabb::from_points(p, p))
+                });
+                let diagonal = Vec3::new(bounds.x.size(), bounds.y.size(), bounds.z.size());
+                0.005 * diagonal.length()
+            });
+
+            let colors = match (column("red"), column("green"), column("blue")) {
+                (Some(r), Some(g), Some(b)) => {
+                    // Integer channels are 0-255, floating point ones 0-1.
+                    let scale = match vertex_element.properties[r].kind {
+                        PropertyKind::Scalar(ScalarType::F32 | ScalarType::F64) => 1.0,
+                        _ => 1.0 / 255.0,
+                    };
+                    Some((r, g, b, scale))
+                }
+                _ => None,
+            };
+
+            let default_material: Arc<dyn Material> = options
+                .material
+                .clone()
+                .unwrap_or_else(|| Arc::new(Lambertian::new(&Color::new(0.8, 0.8, 0.8))));
+            // Points with the same 8-bit color share a material.
+            let mut materials: HashMap<[u8; 3], Arc<dyn Material>> = HashMap::new();
+
+            let mut spheres: Vec<Arc<dyn Hittable>> = Vec::with_capacity(positions.len());
+            for (center, row) in positions.iter().zip(&vertex_rows) {
+                let mat = match (colors, &options.material) {
+                    (Some((r, g, b, scale)), None) => {
+                        let rgb =
+                            [r, g, b].map(|c| (row.values[c].scalar() * scale).clamp(0.0, 1.0));
+                        let key = rgb.map(|c| (c * 255.0).round() as u8);
+                        materials
+                            .entry(key)
+                            .or_insert_with(|| {
+                                let [r, g, b] = rgb.map(srgb_to_linear);
+                                Arc::new(Lambertian::new(&Color::new(r, g, b)))
+                            })
+                            .clone()
+                    }
+                    _ => default_material.clone(),
+                };
+                spheres.push(Arc::new(Sphere::new(center, radius, Some(mat))));
+            }
+            if !spheres.is_empty() {
+                list.add(Arc::new(BvhNode::new(&mut spheres)));
+            }
+        }
+    }
+    Ok(list)
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Clone, Copy)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// Number of header lines, including `end_header`.
+    lines: usize,
+    body_offset: usize,
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Header, (usize, String)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut line = 0;
+
+    loop {
+        let Some(len) = bytes[offset..].iter().position(|&b| b == b'\n') else {
+            return Err((line + 1, "header has no `end_header`".into()));
+        };
+        let text = String::from_utf8_lossy(&bytes[offset..offset + len]);
+        offset += len + 1;
+        line += 1;
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.as_slice() {
+            ["ply"] if line == 1 => {}
+            _ if line == 1 => return Err((line, "not a PLY file".into())),
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, ..] => {
+                return Err((line, format!("unsupported format `{other}`")));
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["element", name, count] => {
+                let count = count
+                    .parse()
+                    .map_err(|_| (line, format!("invalid element count `{count}`")))?;
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (ScalarType::parse(count), ScalarType::parse(item))
+                else {
+                    return Err((line, "unknown property type".into()));
+                };
+                let Some(element) = elements.last_mut() else {
+                    return Err((line, "property before any element".into()));
+                };
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List { count, item },
+                });
+            }
+            ["property", ty, name] => {
+                let ty = ScalarType::parse(ty)
+                    .ok_or_else(|| (line, format!("unknown property type `{ty}`")))?;
+                let Some(element) = elements.last_mut() else {
+                    return Err((line, "property before any element".into()));
+                };
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(ty),
+                });
+            }
+            ["end_header"] => break,
+            _ => return Err((line, format!("unrecognized header line `{}`", text.trim()))),
+        }
+    }
+
+    let format = format.ok_or((line, "header has no `format` line".to_string()))?;
+    Ok(Header {
+        format,
+        elements,
+        lines: line,
+        body_offset: offset,
+    })
+}
+
+enum PlyValue {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+impl PlyValue {
+    fn scalar(&self) -> f64 {
+        match self {
+            PlyValue::Scalar(v) => *v,
+            PlyValue::List(items) => items.first().copied().unwrap_or(0.0),
+        }
+    }
+
+    fn list(&self) -> &[f64] {
+        match self {
+            PlyValue::Scalar(v) => std::slice::from_ref(v),
+            PlyValue::List(items) => items,
+        }
+    }
+}
+
+/// One element row, with its body line in ASCII files.
+struct Row {
+    line: Option<usize>,
+    values: Vec<PlyValue>,
+}
+
+/// Errors carry the body line for ASCII files.
+type BodyError = (Option<usize>, String);
+
+trait BodyReader {
+    fn scalar(&mut self, ty: ScalarType) -> Result<f64, BodyError>;
+    /// Called after each element row.
+    fn end_row(&mut self) -> Result<(), BodyError>;
+    /// The body line of the row being read; `None` in binary data.
+    fn line(&self) -> Option<usize> {
+        None
+    }
+}
+
+fn read_element(reader: &mut dyn BodyReader, element: &Element) -> Result<Vec<Row>, BodyError> {
+    // The count comes from the header and may be wildly wrong, so rows are
+    // only stored as they are actually read.
+    let mut rows = Vec::new();
+    if element.properties.is_empty() {
+        // Nothing to read, however many rows are claimed.
+        return Ok(rows);
+    }
+    for _ in 0..element.count {
+        let mut row = Vec::with_capacity(element.properties.len());
+        for property in &element.properties {
+            row.push(match property.kind {
+                PropertyKind::Scalar(ty) => PlyValue::Scalar(reader.scalar(ty)?),
+                PropertyKind::List { count, item } => {
+                    let n = reader.scalar(count)?;
+                    PlyValue::List(
+                        (0..n as usize)
+                            .map(|_| reader.scalar(item))
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+            });
+        }
+        let line = reader.line();
+        reader.end_row()?;
+        rows.push(Row { line, values: row });
+    }
+    Ok(rows)
+}
+
+/// One element row per line, whitespace separated.
+struct AsciiReader<'a> {
+    lines: std::str::Lines<'a>,
+    words: std::vec::IntoIter<&'a str>,
+    line: usize,
+    row_started: bool,
+}
+
+impl<'a> AsciiReader<'a> {
+    fn new(body: &'a [u8], first_line: usize) -> Self {
+        // Invalid UTF-8 can only appear in malformed numbers, reported below.
+        let text = std::str::from_utf8(body).unwrap_or("");
+        Self {
+            lines: text.lines(),
+            words: Vec::new().into_iter(),
+            line: first_line - 1,
+            row_started: false,
+        }
+    }
+}
+
+impl BodyReader for AsciiReader<'_> {
+    fn scalar(&mut self, _ty: ScalarType) -> Result<f64, BodyError> {
+        loop {
+            if let Some(word) = self.words.next() {
+                return word
+                    .parse()
+                    .map_err(|_| (Some(self.line), format!("`{word}` is not a number")));
+            }
+            if self.row_started {
+                return Err((Some(self.line), "too few values on this line".into()));
+            }
+            match self.lines.next() {
+                Some(text) => {
+                    self.line += 1;
+                    self.words = text.split_whitespace().collect::<Vec<_>>().into_iter();
+                    // Blank lines between rows are skipped.
+                    self.row_started = self.words.len() != 0;
+                }
+                None => return Err((None, "file ends before all elements were read".into())),
+            }
+        }
+    }
+
+    fn line(&self) -> Option<usize> {
+        Some(self.line)
+    }
+
+    fn end_row(&mut self) -> Result<(), BodyError> {
+        if self.words.len() != 0 {
+            return Err((Some(self.line), "too many values on this line".into()));
+        }
+        self.words = Vec::new().into_iter();
+        self.row_started = false;
+        Ok(())
+    }
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BodyReader for BinaryReader<'_> {
+    fn scalar(&mut self, ty: ScalarType) -> Result<f64, BodyError> {
+        let size = ty.size();
+        let Some(bytes) = self.data.get(self.pos..self.pos + size) else {
+            return Err((None, "file ends before all elements were read".into()));
+        };
+        self.pos += size;
+
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        Ok(match ty {
+            ScalarType::I8 => buf[0] as i8 as f64,
+            ScalarType::U8 => buf[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::F64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    fn end_row(&mut self) -> Result<(), BodyError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::Interval;
+
+    /// Writes `bytes` to a fresh file in the temp directory.
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ply-test-{}-{name}", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn load(name: &str, bytes: &[u8]) -> Result<HittableList, PlyError> {
+        let path = temp_file(name, bytes);
+        let result = load_ply(&path);
+        fs::remove_file(path).unwrap();
+        result
+    }
+
+    /// Checks an axis of a bounding box, allowing for its padding.
+    fn assert_spans(axis: Interval, min: f64, max: f64) {
+        assert!((axis.min - min).abs() < 1e-3, "{} != {min}", axis.min);
+        assert!((axis.max - max).abs() < 1e-3, "{} != {max}", axis.max);
+    }
+
+    fn parse_error_line(err: PlyError) -> Option<usize> {
+        match err {
+            PlyError::Parse { line, .. } => line,
+            PlyError::Io { error, .. } => panic!("unexpected I/O error: {error}"),
+        }
+    }
+
+    const ASCII_QUAD: &str = "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+
+    #[test]
+    fn ascii_quad_is_fan_triangulated() {
+        let list = load("quad.ply", ASCII_QUAD.as_bytes()).unwrap();
+        assert_eq!(list.objects().len(), 1);
+
+        let bbox = list.bounding_box();
+        assert_spans(bbox.x, 0.0, 1.0);
+        assert_spans(bbox.y, 0.0, 1.0);
+    }
+
+    #[test]
+    fn binary_little_endian_matches_ascii() {
+        let mut bytes = b"ply
+format binary_little_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+"
+        .to_vec();
+        for p in [[0.0f32, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 3.0, 0.0]] {
+            for c in p {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        bytes.push(3);
+        for i in [0i32, 1, 2] {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+
+        let list = load("binary.ply", &bytes).unwrap();
+        let bbox = list.bounding_box();
+        assert_spans(bbox.x, 0.0, 2.0);
+        assert_spans(bbox.y, 0.0, 3.0);
+    }
+
+    #[test]
+    fn truncated_binary_body_is_an_error() {
+        let bytes = b"ply
+format binary_little_endian 1.0
+element vertex 2
+property float x
+property float y
+property float z
+end_header
+\0\0\0\0";
+        assert_eq!(
+            parse_error_line(load("short.ply", bytes).err().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn point_cloud_becomes_spheres() {
+        let text = "ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+end_header
+0 0 0 255 0 0
+10 0 0 0 255 0
+";
+        let list = load("points.ply", text.as_bytes()).unwrap();
+        assert_eq!(list.objects().len(), 1);
+        assert!(list.bounding_box().x.max > 10.0);
+    }
+
+    #[test]
+    fn huge_element_count_fails_without_allocating() {
+        let text = "ply
+format ascii 1.0
+element vertex 999999999999999
+property float x
+property float y
+property float z
+end_header
+0 0 0
+";
+        assert!(load("huge.ply", text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn face_errors_report_their_line() {
+        let text = ASCII_QUAD.replace("4 0 1 2 3", "3 0 1 7");
+        let err = load("missing.ply", text.as_bytes()).err().unwrap();
+        assert!(err.to_string().contains("missing vertex 7"));
+        assert_eq!(parse_error_line(err), Some(14));
+    }
+
+    #[test]
+    fn header_errors_report_their_line() {
+        let text = ASCII_QUAD.replace("property float y", "property quaternion y");
+        assert_eq!(
+            parse_error_line(load("header.ply", text.as_bytes()).err().unwrap()),
+            Some(5)
+        );
+        assert_eq!(
+            parse_error_line(load("magic.ply", b"obj\n").err().unwrap()),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,529 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    color::{Color, srgb_to_linear, to_rgb8},
+    image::Image,
+    inflate::inflate,
+};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest payload a single stored (uncompressed) deflate block can carry.
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// Writes `image` as an 8-bit RGB PNG.
+///
+/// The pixel data is wrapped in stored deflate blocks, so the file is not
+/// compressed but is readable by any PNG decoder.
+pub fn write_png(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    out.write_all(&SIGNATURE)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&image.width().to_be_bytes());
+    ihdr.extend_from_slice(&image.height().to_be_bytes());
+    // bit depth 8, color type 2 (RGB), deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &ihdr)?;
+
+    let mut raw = Vec::with_capacity(image.height() as usize * (1 + 3 * image.width() as usize));
+    for y in 0..image.height() {
+        // filter type 0: scanline stored as-is
+        raw.push(0);
+        for pixel_color in image.row(y) {
+            raw.extend_from_slice(&to_rgb8(pixel_color));
+        }
+    }
+    write_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+
+    write_chunk(out, b"IEND", &[])
+}
+
+/// Reads a PNG of any standard color type and bit depth, interlaced or not,
+/// into linear colors. Samples are taken to be sRGB encoded; alpha is ignored.
+pub fn read_png(input: &mut impl Read) -> io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    if !data.starts_with(&SIGNATURE) {
+        return Err(invalid("not a PNG file"));
+    }
+
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut compressed = Vec::new();
+    let mut pos = SIGNATURE.len();
+    loop {
+        let (kind, chunk) = read_chunk(&data, &mut pos)?;
+        match &kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            // Bit 5 of the first byte marks chunks that are safe to skip.
+            _ if kind[0] & 0x20 != 0 => {}
+            _ => {
+                return Err(invalid(&format!(
+                    "unsupported critical chunk `{}`",
+                    String::from_utf8_lossy(&kind)
+                )));
+            }
+        }
+    }
+    let header = header.ok_or_else(|| invalid("missing IHDR chunk"))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(invalid("indexed image without a palette"));
+    }
+
+    // Bound both the pixels and the decompressed data by the header before
+    // allocating either.
+    let too_large = || invalid("image is too large");
+    let mut image = Image::try_new(header.width, header.height).ok_or_else(too_large)?;
+    let data_len = header.data_len().ok_or_else(too_large)?;
+    let raw = zlib_decode(&compressed, data_len)?;
+    if raw.len() < data_len {
+        return Err(invalid("image data is too short"));
+    }
+    let mut offset = 0;
+    for pass in header.passes() {
+        let (x0, y0, dx, dy) = pass;
+        let (width, height, stride) = header.pass_size(pass);
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        let len = height * (stride + 1);
+        let scanlines = &raw[offset..offset + len];
+        offset += len;
+
+        let rows = unfilter(scanlines, stride, header.bits_per_pixel().div_ceil(8))?;
+        for (j, row) in rows.chunks_exact(stride).enumerate() {
+            for i in 0..width {
+                let color = header.pixel(row, i, &palette)?;
+                image.set_pixel((x0 + i * dx) as u32, (y0 + j * dy) as u32, color);
+            }
+        }
+    }
+    Ok(image)
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(chunk: &[u8]) -> io::Result<Self> {
+        let [
+            w0,
+            w1,
+            w2,
+            w3,
+            h0,
+            h1,
+            h2,
+            h3,
+            bit_depth,
+            color_type,
+            compression,
+            filter,
+            interlace,
+        ] = *chunk
+        else {
+            return Err(invalid("IHDR chunk has the wrong length"));
+        };
+        let width = u32::from_be_bytes([w0, w1, w2, w3]);
+        let height = u32::from_be_bytes([h0, h1, h2, h3]);
+
+        let depth_ok = match color_type {
+            0 => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(bit_depth, 1 | 2 | 4 | 8),
+            2 | 4 | 6 => matches!(bit_depth, 8 | 16),
+            _ => return Err(invalid(&format!("invalid color type {color_type}"))),
+        };
+        if !depth_ok {
+            return Err(invalid(&format!(
+                "bit depth {bit_depth} is not allowed for color type {color_type}"
+            )));
+        }
+        if compression != 0 || filter != 0 || interlace > 1 {
+            return Err(invalid("unknown compression, filter or interlace method"));
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid("image has no pixels"));
+        }
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced: interlace == 1,
+        })
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    /// Origin and spacing of each pass's pixels: the seven Adam7 passes, or
+    /// the whole image at once.
+    fn passes(&self) -> Vec<(usize, usize, usize, usize)> {
+        if self.interlaced {
+            vec![
+                (0, 0, 8, 8),
+                (4, 0, 8, 8),
+                (0, 4, 4, 8),
+                (2, 0, 4, 4),
+                (0, 2, 2, 4),
+                (1, 0, 2, 2),
+                (0, 1, 1, 2),
+            ]
+        } else {
+            vec![(0, 0, 1, 1)]
+        }
+    }
+
+    /// Width and height in pixels of a pass, and the bytes in each of its
+    /// unfiltered rows.
+    fn pass_size(&self, (x0, y0, dx, dy): (usize, usize, usize, usize)) -> (usize, usize, usize) {
+        let width = (self.width as usize).saturating_sub(x0).div_ceil(dx);
+        let height = (self.height as usize).saturating_sub(y0).div_ceil(dy);
+        (width, height, (width * self.bits_per_pixel()).div_ceil(8))
+    }
+
+    /// Bytes of decompressed data the image needs: every pass's rows, each
+    /// with its filter byte. `None` if that doesn't fit in memory at all.
+    fn data_len(&self) -> Option<usize> {
+        self.passes().into_iter().try_fold(0usize, |total, pass| {
+            let (width, height, stride) = self.pass_size(pass);
+            if width == 0 || height == 0 {
+                return Some(total);
+            }
+            (stride + 1).checked_mul(height)?.checked_add(total)
+        })
+    }
+
+    /// The raw value of `channel` of pixel `i` in an unfiltered row.
+    fn sample(&self, row: &[u8], i: usize, channel: usize) -> u16 {
+        let depth = self.bit_depth as usize;
+        let index = i * self.channels() + channel;
+        match depth {
+            16 => u16::from_be_bytes([row[2 * index], row[2 * index + 1]]),
+            8 => row[index] as u16,
+            _ => {
+                let bit = index * depth;
+                let shift = 8 - depth - bit % 8;
+                ((row[bit / 8] >> shift) & ((1 << depth) - 1)) as u16
+            }
+        }
+    }
+
+    fn pixel(&self, row: &[u8], i: usize, palette: &[[u8; 3]]) -> io::Result<Color> {
+        let max = ((1u32 << self.bit_depth) - 1) as f64;
+        let [r, g, b] = match self.color_type {
+            3 => {
+                let entry = palette
+                    .get(self.sample(row, i, 0) as usize)
+                    .ok_or_else(|| invalid("palette index out of range"))?;
+                entry.map(|c| c as f64 / 255.0)
+            }
+            0 | 4 => [self.sample(row, i, 0) as f64 / max; 3],
+            _ => [0, 1, 2].map(|c| self.sample(row, i, c) as f64 / max),
+        };
+        Ok(Color::new(
+            srgb_to_linear(r),
+            srgb_to_linear(g),
+            srgb_to_linear(b),
+        ))
+    }
+}
+
+/// Returns the chunk starting at `pos` and moves past it, checking its CRC.
+fn read_chunk<'a>(data: &'a [u8], pos: &mut usize) -> io::Result<([u8; 4], &'a [u8])> {
+    let truncated = || invalid("file ends inside a chunk");
+    let header = data.get(*pos..*pos + 8).ok_or_else(truncated)?;
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let kind = [header[4], header[5], header[6], header[7]];
+
+    let body = data.get(*pos + 8..*pos + 8 + len).ok_or_else(truncated)?;
+    let crc = data
+        .get(*pos + 8 + len..*pos + 12 + len)
+        .ok_or_else(truncated)?;
+    let expected = crc32_update(crc32_update(0xffff_ffff, &kind), body) ^ 0xffff_ffff;
+    if u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) != expected {
+        return Err(invalid(&format!(
+            "CRC mismatch in `{}` chunk",
+            String::from_utf8_lossy(&kind)
+        )));
+    }
+
+    *pos += 12 + len;
+    Ok((kind, body))
+}
+
+/// Undoes the per-scanline filters. `scanlines` holds a filter type byte
+/// before each row of `stride` bytes; `bpp` is the pixel size in bytes,
+/// rounded up.
+fn unfilter(scanlines: &[u8], stride: usize, bpp: usize) -> io::Result<Vec<u8>> {
+    let mut rows = vec![0u8; scanlines.len() / (stride + 1) * stride];
+    for (j, line) in scanlines.chunks_exact(stride + 1).enumerate() {
+        let (filter, line) = (line[0], &line[1..]);
+        let (done, rest) = rows.split_at_mut(j * stride);
+        let prior = if j == 0 {
+            None
+        } else {
+            Some(&done[(j - 1) * stride..])
+        };
+        let row = &mut rest[..stride];
+
+        for i in 0..stride {
+            let a = if i >= bpp { row[i - bpp] } else { 0 };
+            let b = prior.map_or(0, |p| p[i]);
+            let c = if i >= bpp {
+                prior.map_or(0, |p| p[i - bpp])
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid(&format!("invalid filter type {filter}"))),
+            };
+            row[i] = line[i].wrapping_add(predictor);
+        }
+    }
+    Ok(rows)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Unwraps a zlib stream of at most `limit` bytes, checking its header and
+/// Adler-32 checksum.
+fn zlib_decode(stream: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    let [cmf, flg, ..] = *stream else {
+        return Err(invalid("zlib stream is too short"));
+    };
+    if cmf & 0x0f != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) || flg & 0x20 != 0 {
+        return Err(invalid("invalid zlib header"));
+    }
+    let Some(body) = stream.get(2..stream.len().saturating_sub(4)) else {
+        return Err(invalid("zlib stream is too short"));
+    };
+    let data = inflate(body, limit).map_err(invalid)?;
+
+    let checksum = &stream[stream.len() - 4..];
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&data) {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+    Ok(data)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("PNG: {message}"))
+}
+
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let crc = crc32_update(crc32_update(0xffff_ffff, kind), data) ^ 0xffff_ffff;
+    out.write_all(&crc.to_be_bytes())
+}
+
+/// Wraps `data` in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut stream = Vec::with_capacity(data.len() + 5 * blocks + 6);
+
+    // CMF: deflate with a 32K window; FLG: no dictionary, check bits for CMF*256+FLG % 31 == 0
+    stream.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        stream.push(last as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(chunk);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |c, &byte| {
+        CRC_TABLE[((c ^ byte as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest run that cannot overflow `b` before reducing.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_image() -> Image {
+        let mut image = Image::new(3, 2);
+        image.set_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        image.set_pixel(1, 0, Color::new(0.0, 0.25, 0.0));
+        image.set_pixel(2, 1, Color::new(0.5, 0.5, 2.0));
+        image
+    }
+
+    /// A PNG holding `raw` as its only image data.
+    fn png_with(ihdr: &[u8], raw: &[u8]) -> Vec<u8> {
+        let mut out = SIGNATURE.to_vec();
+        write_chunk(&mut out, b"IHDR", ihdr).unwrap();
+        write_chunk(&mut out, b"IDAT", &zlib_stored(raw)).unwrap();
+        write_chunk(&mut out, b"IEND", &[]).unwrap();
+        out
+    }
+
+    fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+        ihdr
+    }
+
+    #[test]
+    fn round_trip_keeps_the_8_bit_values() {
+        let image = sample_image();
+        let mut bytes = Vec::new();
+        write_png(&image, &mut bytes).unwrap();
+        let read = read_png(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!((read.width(), read.height()), (3, 2));
+        for (written, read) in image.pixels().iter().zip(read.pixels()) {
+            let expected = to_rgb8(written).map(|c| srgb_to_linear(c as f64 / 255.0));
+            assert_eq!(read.0.e, expected);
+        }
+    }
+
+    #[test]
+    fn reads_packed_palette_pixels() {
+        let mut bytes = SIGNATURE.to_vec();
+        write_chunk(&mut bytes, b"IHDR", &ihdr(3, 1, 1, 3)).unwrap();
+        write_chunk(&mut bytes, b"PLTE", &[0, 0, 0, 255, 255, 255]).unwrap();
+        write_chunk(&mut bytes, b"IDAT", &zlib_stored(&[0, 0b0100_0000])).unwrap();
+        write_chunk(&mut bytes, b"IEND", &[]).unwrap();
+
+        let image = read_png(&mut bytes.as_slice()).unwrap();
+        assert_eq!(image.pixel(0, 0).0.e, [0.0; 3]);
+        assert_eq!(image.pixel(1, 0).0.e, [1.0; 3]);
+        assert_eq!(image.pixel(2, 0).0.e, [0.0; 3]);
+    }
+
+    #[test]
+    fn huge_header_with_little_data_is_an_error() {
+        let bytes = png_with(&ihdr(2_000_000_000, 2_000_000_000, 16, 6), &[0; 16]);
+        let err = read_png(&mut bytes.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn image_data_expanding_past_the_header_is_an_error() {
+        // A 1x1 gray image needs 2 bytes; this zlib stream holds 100,000.
+        let bomb = [
+            vec![
+                0x78, 0xda, 0xed, 0xc1, 0x31, 0x01, 0, 0, 0, 0xc2, 0xa0, 0xf5, 0x4f,
+            ],
+            vec![0x6d, 0x0d, 0x0f, 0xa0],
+            vec![0; 96],
+            vec![0x80, 0x57, 0x03, 0x86, 0xaf, 0x00, 0x01],
+        ]
+        .concat();
+        assert_eq!(zlib_decode(&bomb, 100_000).unwrap().len(), 100_000);
+
+        let mut bytes = SIGNATURE.to_vec();
+        write_chunk(&mut bytes, b"IHDR", &ihdr(1, 1, 8, 0)).unwrap();
+        write_chunk(&mut bytes, b"IDAT", &bomb).unwrap();
+        write_chunk(&mut bytes, b"IEND", &[]).unwrap();
+        let err = read_png(&mut bytes.as_slice()).err().unwrap();
+        assert!(err.to_string().contains("longer than expected"), "{err}");
+    }
+
+    #[test]
+    fn short_image_data_is_an_error() {
+        // Two RGB rows of two pixels need 14 bytes.
+        let bytes = png_with(&ihdr(2, 2, 8, 2), &[0; 13]);
+        assert!(read_png(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn corrupt_chunks_are_errors() {
+        let mut bytes = Vec::new();
+        write_png(&sample_image(), &mut bytes).unwrap();
+
+        let mut bad_crc = bytes.clone();
+        bad_crc[SIGNATURE.len() + 8] ^= 1;
+        assert!(read_png(&mut bad_crc.as_slice()).is_err());
+
+        assert!(read_png(&mut &bytes[1..]).is_err());
+        assert!(read_png(&mut &bytes[..bytes.len() - 4]).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    color::{Color, srgb_to_linear, to_rgb8, write_color},
+    image::Image,
+};
+
+/// Writes `image` as an ASCII (P3) PPM.
+pub fn write_ppm(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "P3")?;
+    writeln!(out, "{} {}", image.width(), image.height())?;
+    writeln!(out, "255")?;
+
+    for pixel_color in image.pixels() {
+        write_color(out, pixel_color)?;
+    }
+    Ok(())
+}
+
+/// Writes `image` as a binary (P6) PPM.
+pub fn write_ppm_binary(image: &Image, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", image.width(), image.height())?;
+
+    let mut scanline = Vec::with_capacity(3 * image.width() as usize);
+    for y in 0..image.height() {
+        scanline.clear();
+        for pixel_color in image.row(y) {
+            scanline.extend_from_slice(&to_rgb8(pixel_color));
+        }
+        out.write_all(&scanline)?;
+    }
+    Ok(())
+}
+
+/// Reads an ASCII (P3) or binary (P6) PPM with any maximum value into linear
+/// colors, taking the samples to be sRGB encoded.
+pub fn read_ppm(input: &mut impl Read) -> io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut pos = 0;
+    let magic = next_token(&data, &mut pos)?;
+    let binary = match magic {
+        b"P3" => false,
+        b"P6" => true,
+        _ => return Err(invalid("not a P3 or P6 file")),
+    };
+    let width = next_number(&data, &mut pos)?;
+    let height = next_number(&data, &mut pos)?;
+    let max = next_number(&data, &mut pos)?;
+    if width == 0 || height == 0 || !(1..=65535).contains(&max) {
+        return Err(invalid("invalid width, height or maximum value"));
+    }
+
+    // Every sample takes at least one byte, so a header asking for more than
+    // the file holds is rejected before anything is allocated.
+    let too_short = || invalid("pixel data is too short");
+    let size = if binary && max > 255 { 2 } else { 1 };
+    let count = 3usize
+        .checked_mul(width as usize)
+        .and_then(|n| n.checked_mul(height as usize))
+        .ok_or_else(too_short)?;
+    let raster_len = count.checked_mul(size).ok_or_else(too_short)?;
+    if raster_len > data.len().saturating_sub(pos) {
+        return Err(too_short());
+    }
+    let mut image = Image::try_new(width, height).ok_or_else(|| invalid("image is too large"))?;
+
+    let samples: Vec<u32> = if binary {
+        // Exactly one whitespace byte separates the header from the raster.
+        pos += 1;
+        let raster = data.get(pos..pos + raster_len).ok_or_else(too_short)?;
+        match size {
+            1 => raster.iter().map(|&b| b as u32).collect(),
+            _ => raster
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as u32)
+                .collect(),
+        }
+    } else {
+        (0..count)
+            .map(|_| next_number(&data, &mut pos))
+            .collect::<io::Result<_>>()?
+    };
+
+    for (pixel, rgb) in image.pixels_mut().iter_mut().zip(samples.chunks_exact(3)) {
+        let [r, g, b] =
+            [rgb[0], rgb[1], rgb[2]].map(|c| srgb_to_linear(c.min(max) as f64 / max as f64));
+        *pixel = Color::new(r, g, b);
+    }
+    Ok(image)
+}
+
+/// The next whitespace-separated token, skipping `#` comments.
+fn next_token<'a>(data: &'a [u8], pos: &mut usize) -> io::Result<&'a [u8]> {
+    loop {
+        match data.get(*pos) {
+            Some(b'#') => {
+                while data.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => *pos += 1,
+            Some(_) => break,
+            None => return Err(invalid("file ends early")),
+        }
+    }
+    let start = *pos;
+    while data.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    Ok(&data[start..*pos])
+}
+
+fn next_number(data: &[u8], pos: &mut usize) -> io::Result<u32> {
+    let token = next_token(data, pos)?;
+    std::str::from_utf8(token)
+        .ok()
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| {
+            invalid(&format!(
+                "`{}` is not a number",
+                String::from_utf8_lossy(token)
+            ))
+        })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("PPM: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::to_rgb8;
+
+    fn sample_image() -> Image {
+        let mut image = Image::new(2, 2);
+        image.set_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        image.set_pixel(1, 0, Color::new(0.0, 0.25, 0.0));
+        image.set_pixel(1, 1, Color::new(0.5, 0.5, 2.0));
+        image
+    }
+
+    fn assert_round_trip(write: fn(&Image, &mut Vec<u8>) -> io::Result<()>) {
+        let image = sample_image();
+        let mut bytes = Vec::new();
+        write(&image, &mut bytes).unwrap();
+        let read = read_ppm(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!((read.width(), read.height()), (2, 2));
+        for (written, read) in image.pixels().iter().zip(read.pixels()) {
+            let expected = to_rgb8(written).map(|c| srgb_to_linear(c as f64 / 255.0));
+            assert_eq!(read.0.e, expected);
+        }
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        assert_round_trip(write_ppm);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        assert_round_trip(write_ppm_binary);
+    }
+
+    #[test]
+    fn reads_comments_and_16_bit_samples() {
+        let mut bytes = b"P6 # a comment\n1 1\n# another\n65535\n".to_vec();
+        bytes.extend_from_slice(&[0xff, 0xff, 0, 0, 0xff, 0xff]);
+        let image = read_ppm(&mut bytes.as_slice()).unwrap();
+        assert_eq!(image.pixel(0, 0).0.e, [1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn oversized_headers_are_errors() {
+        for header in [
+            "P6 4294967295 4294967295 255\n\0\0\0",
+            "P6 2000000000 2000000000 65535\n\0\0\0",
+            "P3 4294967295 4294967295 255\n0 0 0",
+        ] {
+            let err = read_ppm(&mut header.as_bytes()).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{header}");
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        assert!(read_ppm(&mut &b"P3 2 1 255\n0 0 0 1 2"[..]).is_err());
+        assert!(read_ppm(&mut &b"P6 2 1 255\n\0\0\0\0\0"[..]).is_err());
+        assert!(read_ppm(&mut &b"P6 0 1 255\n"[..]).is_err());
+        assert!(read_ppm(&mut &b"P5 1 1 255\n\0"[..]).is_err());
+    }
+}
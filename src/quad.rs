@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    rtweekend::random_f64,
+    vec3::{Point3, Vec3, dot, unit_vector},
+};
+
+/// A parallelogram spanned by the edges `u` and `v` from the corner `q`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: &Point3, u: &Vec3, v: &Vec3, mat: Option<Arc<dyn Material>>) -> Self {
+        let n = u.cross(v);
+        let normal = unit_vector(&n);
+        let d = dot(&normal, q);
+        let w = n / dot(&n, &n);
+
+        // Bound both diagonals so edges pointing in any direction are covered.
+        let bbox_diagonal1 = Aabb::from_points(q, &(*q + *u + *v));
+        let bbox_diagonal2 = Aabb::from_points(&(*q + *u), &(*q + *v));
+
+        Self {
+            q: *q,
+            u: *u,
+            v: *v,
+            w,
+            mat,
+            bbox: Aabb::from_boxes(&bbox_diagonal1, &bbox_diagonal2),
+            normal,
+            d,
+            area: n.length(),
+        }
+    }
+
+    /// Given the hit point in plane coordinates, reports whether it lies within the quad.
+    fn is_interior(a: f64, b: f64) -> bool {
+        let unit_interval = Interval::new(0.0, 1.0);
+        unit_interval.contains(a) && unit_interval.contains(b)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let denom = dot(&self.normal, r.direction());
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - dot(&self.normal, r.origin())) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = dot(&self.w, &planar_hitpt_vector.cross(&self.v));
+        let beta = dot(&self.w, &self.u.cross(&planar_hitpt_vector));
+        if !Self::is_interior(alpha, beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            p: intersection,
+            u: alpha,
+            v: beta,
+            mat: self.mat.clone(),
+            ..HitRecord::default()
+        };
+        rec.set_face_normal(r, &self.normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        let Some(rec) = self.hit(r, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+
+        // Points are picked uniformly by area; convert that density to one
+        // over solid angle as seen from the ray's origin.
+        let distance_squared = rec.t * rec.t * r.direction().length_squared();
+        let cosine = dot(r.direction(), &self.normal).abs() / r.direction().length();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let p = self.q + (random_f64() * self.u) + (random_f64() * self.v);
+        p - *origin
+    }
+}
+
+/// The six sides of the axis-aligned box with opposite corners `a` and `b`.
+pub fn make_box(a: &Point3, b: &Point3, mat: Option<Arc<dyn Material>>) -> HittableList {
+    let mut sides: HittableList = HittableList::default();
+
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    let faces = [
+        (Point3::new(min.x(), min.y(), max.z()), dx, dy), // front
+        (Point3::new(max.x(), min.y(), max.z()), -dz, dy), // right
+        (Point3::new(max.x(), min.y(), min.z()), -dx, dy), // back
+        (Point3::new(min.x(), min.y(), min.z()), dz, dy), // left
+        (Point3::new(min.x(), max.y(), max.z()), dx, -dz), // top
+        (Point3::new(min.x(), min.y(), min.z()), dx, dz), // bottom
+    ];
+    for (q, u, v) in faces {
+        sides.add(Arc::new(Quad::new(&q, &u, &v, mat.clone())));
+    }
+
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A slanted parallelogram in the plane z = 1, facing +Z.
+    fn quad() -> Quad {
+        Quad::new(
+            &Point3::new(1.0, 1.0, 1.0),
+            &Vec3::new(2.0, 0.0, 0.0),
+            &Vec3::new(1.0, 3.0, 0.0),
+            None,
+        )
+    }
+
+    fn ray_t() -> Interval {
+        Interval::new(0.001, f64::INFINITY)
+    }
+
+    #[test]
+    fn hits_report_the_edge_coordinates() {
+        let quad = quad();
+        // q + 0.25 u + 0.5 v.
+        let target = Point3::new(2.0, 2.5, 1.0);
+        let origin = Point3::new(0.0, 0.0, 5.0);
+        let rec = quad
+            .hit(&Ray::new(&origin, &(target - origin)), ray_t())
+            .unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!((rec.p - target).length() < 1e-12);
+        assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal.e, [0.0, 0.0, 1.0]);
+
+        // From below, the normal still faces the ray.
+        let origin = Point3::new(2.0, 2.5, -3.0);
+        let rec = quad
+            .hit(&Ray::new(&origin, &(target - origin)), ray_t())
+            .unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal.e, [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn misses_outside_the_edges_and_along_the_plane() {
+        let quad = quad();
+        let origin = Point3::new(0.0, 0.0, 5.0);
+        // Inside the bounding box, but past the slanted edge.
+        for target in [Point3::new(1.5, 3.5, 1.0), Point3::new(0.9, 1.5, 1.0)] {
+            assert!(
+                quad.hit(&Ray::new(&origin, &(target - origin)), ray_t())
+                    .is_none()
+            );
+        }
+        let along = Ray::new(&Point3::new(0.0, 2.0, 1.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert!(quad.hit(&along, ray_t()).is_none());
+        // Behind the ray's origin.
+        let away = Ray::new(&Point3::new(2.0, 2.5, 3.0), &Vec3::new(0.0, 0.0, 1.0));
+        assert!(quad.hit(&away, ray_t()).is_none());
+    }
+
+    #[test]
+    fn pdf_converts_area_to_solid_angle() {
+        let quad = quad();
+        // Straight down from 4 above: distance^2 / (cos * area) = 16 / 6.
+        let origin = Point3::new(2.0, 2.5, 5.0);
+        let down = Ray::new(&origin, &Vec3::new(0.0, 0.0, -2.0));
+        assert!((quad.pdf_value(&down) - 16.0 / 6.0).abs() < 1e-12);
+
+        // At a slant the quad looks smaller, so each direction is likelier.
+        let slant = Ray::new(&Point3::new(-1.0, 2.5, 5.0), &Vec3::new(3.0, 0.0, -4.0));
+        let expected = 25.0 / (0.8 * 6.0);
+        assert!((quad.pdf_value(&slant) - expected).abs() < 1e-12);
+
+        let miss = Ray::new(&origin, &Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(quad.pdf_value(&miss), 0.0);
+
+        for _ in 0..100 {
+            let direction = quad.random(&origin, 0.0);
+            assert!(quad.hit(&Ray::new(&origin, &direction), ray_t()).is_some());
+        }
+    }
+
+    #[test]
+    fn boxes_are_closed() {
+        let sides = make_box(
+            &Point3::new(1.0, 2.0, 3.0),
+            &Point3::new(-1.0, 0.0, 0.0),
+            None,
+        );
+        assert_eq!(sides.objects().len(), 6);
+        let bbox = sides.bounding_box();
+        assert!(bbox.x.contains(-1.0) && bbox.x.contains(1.0));
+        assert!(bbox.z.contains(0.0) && bbox.z.contains(3.0));
+        // A ray from inside leaves through exactly one side, in every direction.
+        let origin = Point3::new(0.2, 1.1, 1.4);
+        for direction in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(-0.3, 0.7, -0.2),
+        ] {
+            let r = Ray::new(&origin, &direction);
+            let hits = sides
+                .objects()
+                .iter()
+                .filter(|side| side.hit(&r, ray_t()).is_some())
+                .count();
+            assert_eq!(hits, 1, "{direction:?}");
+        }
+    }
+}
@@ -1,15 +1,25 @@
 use crate::vec3::{Point3, Vec3};
 
+#[derive(Default)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    tm: f64,
 }
 
 impl Ray {
+    /// A ray at time zero.
     pub fn new(orig: &Point3, dir: &Vec3) -> Self {
+        Self::with_time(orig, dir, 0.0)
+    }
+
+    /// A ray cast at `time`, the moment within the exposure that moving
+    /// objects are placed for.
+    pub fn with_time(orig: &Point3, dir: &Vec3, time: f64) -> Self {
         Self {
-            orig: orig.clone(),
-            dir: dir.clone(),
+            orig: *orig,
+            dir: *dir,
+            tm: time,
         }
     }
 
@@ -21,7 +31,11 @@ impl Ray {
         &self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.tm
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
-        self.orig.clone() + t * self.dir.clone()
+        self.orig + t * self.dir
     }
 }
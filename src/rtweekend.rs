@@ -1,12 +1,21 @@
-use rand::Rng;
-use std::f64::consts::PI;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{cell::RefCell, f64::consts::PI};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_os_rng());
+}
 
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+/// Restarts the calling thread's random sequence from `seed`.
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
 pub fn random_f64() -> f64 {
-    rand::rng().random()
+    RNG.with(|rng| rng.borrow_mut().random())
 }
 
 pub fn random_f64_within(min: f64, max: f64) -> f64 {
@@ -0,0 +1,1440 @@
+//! Loads scenes from TOML files.
+//!
+//! ```toml
+//! [camera]
+//! aspect_ratio = 1.7778
+//! image_width = 400
+//! lookfrom = [13, 2, 3]
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = [0.5, 0.5, 0.5]
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0, -1000, 0]
+//! radius = 1000
+//! material = "ground"
+//! ```
+//!
+//! The camera's `background` is a color, a `gradient` table with `bottom` and
+//! `top` colors, or an `environment` table naming an equirectangular image
+//! (ideally a Radiance .hdr) at `path`, with optional `rotation` in degrees
+//! about the vertical axis and `intensity`. It defaults to a white-to-blue sky.
+//!
+//! Object types are `sphere` (`center`, `radius`), `quad` (corner `q` and
+//! edges `u`, `v`), `box` (opposite corners `min`, `max`), `triangle` (`a`,
+//! `b`, `c`), `mesh` (`vertices`, `faces`, optional `normals` and `uvs`),
+//! `obj` (a Wavefront file at `path`; its MTL materials apply unless the
+//! object names a `material`) and `ply` (a PLY mesh or point cloud at `path`,
+//! with an optional `point_radius` for the points).
+//!
+//! Any object can be placed with a `transform` table, applied as `scale` (a
+//! factor or one per axis), then `rotate` (degrees about X, Y and Z in that
+//! order), then `translate`, e.g.
+//! `transform = { rotate = [0, 15, 0], translate = [265, 0, 295] }`.
+//!
+//! A `constant_medium` is smoke or fog of uniform `density` (the chance of
+//! scattering per unit distance) with an `albedo` color or texture, white by
+//! default. It fills its `boundary`, an inline object table such as
+//! `boundary = { type = "sphere", center = [0, 0, 0], radius = 2 }`, or the
+//! whole scene when the boundary is left out.
+//!
+//! A `grid_medium` fills the box between corners `min` and `max` with a
+//! `density` that varies through it, multiplied by `density_scale`. The
+//! densities come from `{ type = "noise", resolution = [64, 64, 64] }` (a
+//! procedural cloud, also taking `scale`, `octaves` and `seed`) or from a
+//! grid file at `path`: text holding the three dimensions and then one value
+//! per voxel, X fastest, or, given its `resolution`, raw little-endian
+//! 32-bit floats. Its `albedo` (white by default) and `emission` are each a
+//! color or a grid file with three values per voxel; only the light the
+//! albedo absorbs is re-emitted, so a glowing medium needs a darker albedo.
+//!
+//! Objects can also move during the exposure, which runs from the camera's
+//! `shutter_open` to its `shutter_close`, times from 0 to 1 (0 and 1 by
+//! default). A sphere with a `center_end` travels there from `center`, and an
+//! object with a `transform_end` moves from its `transform` (or where it was
+//! defined) to that pose, in both cases between time 0 and time 1.
+//!
+//! Objects name a material from `[materials]` or give one inline, e.g.
+//! `material = { type = "dielectric", refraction_index = 1.5 }`. Material
+//! types are `lambertian` (`albedo`), `metal` (`albedo`, `fuzz`),
+//! `dielectric` (`refraction_index`) and `diffuse_light` (`emit`, which like
+//! `albedo` is a color or texture and may exceed 1). Spheres, quads,
+//! triangles and meshes made of a `diffuse_light`, moved or not, are sampled
+//! directly as lights; other emitters light only what paths happen to find
+//! them from.
+//!
+//! The `albedo` of a lambertian or metal material is a color, the name of an
+//! entry in `[textures]`, or an inline texture: `solid` (`color`),
+//! `checker` (`scale`, and `even`/`odd` albedos of their own), `image`
+//! (a PPM or PNG at `path`, with optional `wrap` of `repeat`, `mirror` or
+//! `clamp` and `filter` of `bilinear` or `nearest`), or one of the procedural
+//! `noise` (`scale`, `octaves`), `marble` (`scale`, `turbulence`, `octaves`,
+//! `base` and `vein` colors) and `wood` (the same, with `light` and `dark`)
+//! textures, which also take a `seed`. For example,
+//! `albedo = { type = "checker", scale = 0.32, even = [0.2, 0.3, 0.1], odd = [0.9, 0.9, 0.9] }`.
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    background::Background,
+    camera::CameraBuilder,
+    color::Color,
+    constant_medium::ConstantMedium,
+    environment::EnvironmentMap,
+    grid_medium::GridMedium,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    obj::load_obj_with_material,
+    ply::{PlyOptions, load_ply_with},
+    quad::{Quad, make_box},
+    sphere::Sphere,
+    texture::{
+        CheckerTexture, Filter, ImageTexture, MarbleTexture, NoiseTexture, SolidColor, Texture,
+        WoodTexture, WrapMode,
+    },
+    toml::{self, Table, TomlError, Value, ValueKind},
+    transform::{AnimatedTransform, Pose, Transform},
+    triangle::{MeshFace, Triangle, TriangleMesh},
+    vec3::{Point3, Vec3},
+    voxel_grid::VoxelGrid,
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// A syntax error or a value the loader cannot use, with its 1-based line.
+    Invalid {
+        line: usize,
+        message: String,
+    },
+}
+
+impl SceneError {
+    fn invalid(line: usize, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Invalid { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<TomlError> for SceneError {
+    fn from(err: TomlError) -> Self {
+        Self::invalid(err.line, err.message)
+    }
+}
+
+/// A world plus the camera settings it was described with. The camera is
+/// returned as a builder so callers can still override individual fields.
+pub struct Scene {
+    pub camera: CameraBuilder,
+    pub world: HittableList,
+    /// Emitters in `world` that the camera samples directly.
+    pub lights: HittableList,
+}
+
+impl Scene {
+    /// Loads a scene file. Files it refers to, such as OBJ meshes, are
+    /// resolved relative to its directory.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::parse_in(&fs::read_to_string(path)?, base)
+    }
+
+    /// Parses a scene, resolving referenced files against the working directory.
+    pub fn parse(source: &str) -> Result<Self, SceneError> {
+        Self::parse_in(source, Path::new(""))
+    }
+
+    fn parse_in(source: &str, base: &Path) -> Result<Self, SceneError> {
+        let root = toml::parse(source)?;
+        check_keys(
+            &root,
+            "the scene",
+            &["camera", "textures", "materials", "objects"],
+        )?;
+
+        let camera = match table(&root, "camera")? {
+            Some(t) => load_camera(t, base)?,
+            None => CameraBuilder::default(),
+        };
+
+        // Textures may refer to those defined before them.
+        let mut defs = Definitions {
+            base,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+        };
+        if let Some(t) = table(&root, "textures")? {
+            for (name, value) in t.iter() {
+                let ValueKind::Table(definition) = &value.kind else {
+                    return Err(type_error(name, "a table", value));
+                };
+                let texture = load_texture(definition, &defs)?;
+                defs.textures.insert(name.to_string(), texture);
+            }
+        }
+        if let Some(t) = table(&root, "materials")? {
+            for (name, value) in t.iter() {
+                let ValueKind::Table(definition) = &value.kind else {
+                    return Err(type_error(name, "a table", value));
+                };
+                let material = load_material(definition, &defs)?;
+                defs.materials.insert(name.to_string(), material);
+            }
+        }
+
+        let mut world: HittableList = HittableList::default();
+        let mut lights: HittableList = HittableList::default();
+        for value in array(&root, "objects")?.unwrap_or_default() {
+            let ValueKind::Table(object) = &value.kind else {
+                return Err(type_error("objects", "an array of tables", value));
+            };
+            let (object, is_light) = load_object(object, &defs)?;
+            if is_light {
+                lights.add(object.clone());
+            }
+            world.add(object);
+        }
+
+        Ok(Self {
+            camera,
+            world,
+            lights,
+        })
+    }
+}
+
+/// Builds one entry of `objects`, placed by its `transform` if it has one,
+/// and tells whether it is a light the camera can sample: an untransformed
+/// sphere or quad made of an emissive material.
+fn load_object(
+    object: &Table,
+    defs: &Definitions,
+) -> Result<(Arc<dyn Hittable>, bool), SceneError> {
+    let kind = require(string(object, "type")?, object, "type")?;
+    let mut is_light = false;
+    let hittable: Arc<dyn Hittable> = match kind {
+        "sphere" => {
+            check_keys(
+                object,
+                "a sphere",
+                &[
+                    "type",
+                    "center",
+                    "center_end",
+                    "radius",
+                    "material",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let center = require(vec3(object, "center")?, object, "center")?;
+            let center_end = vec3(object, "center_end")?.unwrap_or(center);
+            let radius = require(number(object, "radius")?, object, "radius")?;
+            let mat = object_material(object, defs)?;
+            is_light = mat.as_ref().is_some_and(|mat| mat.is_emissive());
+            Arc::new(Sphere::moving(&center, &center_end, radius, mat))
+        }
+        "quad" => {
+            check_keys(
+                object,
+                "a quad",
+                &[
+                    "type",
+                    "q",
+                    "u",
+                    "v",
+                    "material",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let q = require(vec3(object, "q")?, object, "q")?;
+            let u = require(vec3(object, "u")?, object, "u")?;
+            let v = require(vec3(object, "v")?, object, "v")?;
+            let mat = object_material(object, defs)?;
+            is_light = mat.as_ref().is_some_and(|mat| mat.is_emissive());
+            Arc::new(Quad::new(&q, &u, &v, mat))
+        }
+        "box" => {
+            check_keys(
+                object,
+                "a box",
+                &[
+                    "type",
+                    "min",
+                    "max",
+                    "material",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let min = require(vec3(object, "min")?, object, "min")?;
+            let max = require(vec3(object, "max")?, object, "max")?;
+            let mat = object_material(object, defs)?;
+            Arc::new(make_box(&min, &max, mat))
+        }
+        "triangle" => {
+            check_keys(
+                object,
+                "a triangle",
+                &[
+                    "type",
+                    "a",
+                    "b",
+                    "c",
+                    "material",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let a = require(vec3(object, "a")?, object, "a")?;
+            let b = require(vec3(object, "b")?, object, "b")?;
+            let c = require(vec3(object, "c")?, object, "c")?;
+            let mat = object_material(object, defs)?;
+            is_light = mat.as_ref().is_some_and(|mat| mat.is_emissive());
+            Arc::new(Triangle::new(&a, &b, &c, mat))
+        }
+        "mesh" => {
+            let mat = object_material(object, defs)?;
+            is_light = mat.as_ref().is_some_and(|mat| mat.is_emissive());
+            Arc::new(load_mesh(object, mat)?)
+        }
+        "obj" => {
+            check_keys(
+                object,
+                "an obj",
+                &["type", "path", "material", "transform", "transform_end"],
+            )?;
+            let file = require(string(object, "path")?, object, "path")?;
+            let mat = object_material(object, defs)?;
+            let meshes = load_obj_with_material(defs.base.join(file), mat).map_err(|err| {
+                SceneError::invalid(
+                    object.get("path").map_or(object.line, |v| v.line),
+                    format!("cannot load `{file}`: {err}"),
+                )
+            })?;
+            Arc::new(meshes)
+        }
+        "ply" => {
+            check_keys(
+                object,
+                "a ply",
+                &[
+                    "type",
+                    "path",
+                    "point_radius",
+                    "material",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let file = require(string(object, "path")?, object, "path")?;
+            let options = PlyOptions {
+                point_radius: positive(object, "point_radius")?,
+                material: object_material(object, defs)?,
+            };
+            let list = load_ply_with(defs.base.join(file), &options).map_err(|err| {
+                SceneError::invalid(
+                    object.get("path").map_or(object.line, |v| v.line),
+                    format!("cannot load `{file}`: {err}"),
+                )
+            })?;
+            Arc::new(list)
+        }
+        "constant_medium" => {
+            check_keys(
+                object,
+                "a constant_medium",
+                &[
+                    "type",
+                    "boundary",
+                    "density",
+                    "albedo",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let density = require(positive(object, "density")?, object, "density")?;
+            let albedo = texture(object, "albedo", defs)?
+                .unwrap_or_else(|| Arc::new(SolidColor::new(&Color::new(1.0, 1.0, 1.0))));
+            match table(object, "boundary")? {
+                Some(boundary) => Arc::new(ConstantMedium::from_texture(
+                    load_object(boundary, defs)?.0,
+                    density,
+                    albedo,
+                )),
+                None => Arc::new(ConstantMedium::global_from_texture(density, albedo)),
+            }
+        }
+        "grid_medium" => {
+            check_keys(
+                object,
+                "a grid_medium",
+                &[
+                    "type",
+                    "min",
+                    "max",
+                    "density",
+                    "density_scale",
+                    "albedo",
+                    "emission",
+                    "transform",
+                    "transform_end",
+                ],
+            )?;
+            let min = require(vec3(object, "min")?, object, "min")?;
+            let max = require(vec3(object, "max")?, object, "max")?;
+            let density = require(density_grid(object, "density", defs)?, object, "density")?;
+            let mut medium = GridMedium::new(&min, &max, density).ok_or_else(|| {
+                SceneError::invalid(
+                    object.get("max").map_or(object.line, |v| v.line),
+                    "`min` and `max` must differ along every axis",
+                )
+            })?;
+            if let Some(scale) = non_negative(object, "density_scale")? {
+                medium = medium.with_density_scale(scale);
+            }
+            if let Some(albedo) = color_grid(object, "albedo", defs)? {
+                medium = medium.with_albedo(albedo);
+            }
+            if let Some(emission) = color_grid(object, "emission", defs)? {
+                medium = medium.with_emission(emission);
+            }
+            Arc::new(medium)
+        }
+        _ => {
+            return Err(SceneError::invalid(
+                object.get("type").map_or(object.line, |v| v.line),
+                format!(
+                    "unknown object type `{kind}` (expected `sphere`, `quad`, `box`, \
+                     `triangle`, `mesh`, `obj`, `ply`, `constant_medium` or `grid_medium`)"
+                ),
+            ));
+        }
+    };
+    let poses = (
+        load_pose(object, "transform")?,
+        load_pose(object, "transform_end")?,
+    );
+    Ok(match poses {
+        (None, None) => (hittable, is_light),
+        (Some(pose), None) => (
+            Arc::new(
+                Transform::new(hittable, pose.matrix())
+                    .expect("load_pose only accepts invertible poses"),
+            ),
+            is_light,
+        ),
+        (start, Some(end)) => (
+            Arc::new(AnimatedTransform::new(
+                hittable,
+                start.unwrap_or_default(),
+                end,
+            )),
+            is_light,
+        ),
+    })
+}
+
+/// A grid medium's densities: a `noise` cloud (`resolution`, `scale`,
+/// `octaves`, `seed`) or a file at `path`, read as text unless its
+/// `resolution` is given for a headerless file of floats.
+fn density_grid(
+    t: &Table,
+    key: &str,
+    defs: &Definitions,
+) -> Result<Option<VoxelGrid<f64>>, SceneError> {
+    let Some(source) = table(t, key)? else {
+        return Ok(None);
+    };
+    if string(source, "type")? == Some("noise") {
+        check_keys(
+            source,
+            "a noise grid",
+            &["type", "resolution", "scale", "octaves", "seed"],
+        )?;
+        let resolution = grid_resolution(source)?.unwrap_or([64; 3]);
+        let scale = positive(source, "scale")?.unwrap_or(4.0);
+        let octaves = integer(source, "octaves", 1)?.unwrap_or(4);
+        let seed = integer(source, "seed", 0)?.unwrap_or(0) as u64;
+        return Ok(Some(VoxelGrid::noise_cloud(
+            resolution, seed, scale, octaves,
+        )));
+    }
+    load_grid(
+        source,
+        defs,
+        VoxelGrid::<f64>::load,
+        VoxelGrid::<f64>::load_raw,
+    )
+    .map(Some)
+}
+
+/// A grid medium's albedo or emission: one color, or a file of colors laid
+/// out like `density_grid`'s.
+fn color_grid(
+    t: &Table,
+    key: &str,
+    defs: &Definitions,
+) -> Result<Option<VoxelGrid<Color>>, SceneError> {
+    let Some(value) = t.get(key) else {
+        return Ok(None);
+    };
+    match &value.kind {
+        ValueKind::Array(_) => {
+            let color = require(color(t, key)?, t, key)?;
+            Ok(Some(VoxelGrid::uniform(color)))
+        }
+        ValueKind::Table(source) => load_grid(
+            source,
+            defs,
+            VoxelGrid::<Color>::load,
+            VoxelGrid::<Color>::load_raw,
+        )
+        .map(Some),
+        _ => Err(type_error(key, "a color or table", value)),
+    }
+}
+
+fn load_grid<T>(
+    source: &Table,
+    defs: &Definitions,
+    load: impl FnOnce(PathBuf) -> io::Result<VoxelGrid<T>>,
+    load_raw: impl FnOnce(PathBuf, [usize; 3]) -> io::Result<VoxelGrid<T>>,
+) -> Result<VoxelGrid<T>, SceneError> {
+    check_keys(source, "a grid file", &["path", "resolution"])?;
+    let file = require(string(source, "path")?, source, "path")?;
+    let path = defs.base.join(file);
+    let grid = match grid_resolution(source)? {
+        Some(resolution) => load_raw(path, resolution),
+        None => load(path),
+    };
+    grid.map_err(|err| {
+        SceneError::invalid(
+            source.get("path").map_or(source.line, |v| v.line),
+            format!("cannot load `{file}`: {err}"),
+        )
+    })
+}
+
+/// The most voxels a grid `resolution` may ask for: 512^3, a gigabyte of
+/// densities.
+const MAX_VOXELS: usize = 1 << 27;
+
+fn grid_resolution(t: &Table) -> Result<Option<[usize; 3]>, SceneError> {
+    let Some(value) = t.get("resolution") else {
+        return Ok(None);
+    };
+    let dims = match fixed_numbers::<3>(value) {
+        Some(dims)
+            if dims
+                .iter()
+                .all(|&n| n >= 1.0 && n.fract() == 0.0 && n <= 4096.0) =>
+        {
+            dims.map(|n| n as usize)
+        }
+        _ => {
+            return Err(type_error(
+                "resolution",
+                "an array of three whole numbers from 1 to 4096",
+                value,
+            ));
+        }
+    };
+    if dims.iter().product::<usize>() > MAX_VOXELS {
+        return Err(SceneError::invalid(
+            value.line,
+            format!("`resolution` must have at most {MAX_VOXELS} voxels in all"),
+        ));
+    }
+    Ok(Some(dims))
+}
+
+/// An inline mesh: `faces` index into `vertices` and, when given, into the
+/// per-vertex `normals` and `uvs`.
+fn load_mesh(t: &Table, mat: Option<Arc<dyn Material>>) -> Result<TriangleMesh, SceneError> {
+    check_keys(
+        t,
+        "a mesh",
+        &[
+            "type",
+            "vertices",
+            "faces",
+            "normals",
+            "uvs",
+            "material",
+            "transform",
+            "transform_end",
+        ],
+    )?;
+
+    let vertices: Vec<Point3> = require(tuples::<3>(t, "vertices")?, t, "vertices")?
+        .into_iter()
+        .map(|[x, y, z]| Point3::new(x, y, z))
+        .collect();
+    let normals: Vec<Vec3> = tuples::<3>(t, "normals")?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|[x, y, z]| Vec3::new(x, y, z))
+        .collect();
+    let uvs: Vec<(f64, f64)> = tuples::<2>(t, "uvs")?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|[u, v]| (u, v))
+        .collect();
+
+    for (key, len) in [("normals", normals.len()), ("uvs", uvs.len())] {
+        if len != 0 && len != vertices.len() {
+            return Err(SceneError::invalid(
+                t.get(key).map_or(t.line, |v| v.line),
+                format!(
+                    "`{key}` has {len} entries but there are {} vertices",
+                    vertices.len()
+                ),
+            ));
+        }
+    }
+
+    let mut faces = Vec::new();
+    for (i, [a, b, c]) in require(tuples::<3>(t, "faces")?, t, "faces")?
+        .into_iter()
+        .enumerate()
+    {
+        let indices = [a, b, c];
+        if indices
+            .iter()
+            .any(|&k| k.fract() != 0.0 || k < 0.0 || k as usize >= vertices.len())
+        {
+            return Err(SceneError::invalid(
+                t.get("faces").map_or(t.line, |v| v.line),
+                format!(
+                    "face {i} must index existing vertices (0 to {})",
+                    vertices.len().saturating_sub(1)
+                ),
+            ));
+        }
+        let indices = indices.map(|k| k as usize);
+        faces.push(MeshFace {
+            vertices: indices,
+            normals: (!normals.is_empty()).then_some(indices),
+            uvs: (!uvs.is_empty()).then_some(indices),
+        });
+    }
+
+    TriangleMesh::new(vertices, normals, uvs, faces, mat)
+        .map_err(|e| SceneError::invalid(t.get("faces").map_or(t.line, |v| v.line), e.to_string()))
+}
+
+/// An object's `transform` or `transform_end` table: a `scale` (one
+/// factor or one per axis), then a `rotate` about the X, Y and Z axes in turn
+/// (in degrees), then a `translate`.
+fn load_pose(object: &Table, key: &str) -> Result<Option<Pose>, SceneError> {
+    let Some(t) = table(object, key)? else {
+        return Ok(None);
+    };
+    check_keys(t, "a transform", &["scale", "rotate", "translate"])?;
+
+    let mut pose = Pose::default();
+    if let Some(value) = t.get("scale") {
+        pose.scale = match (&value.kind, fixed_numbers::<3>(value)) {
+            (ValueKind::Number(s), _) => Vec3::new(*s, *s, *s),
+            (_, Some([x, y, z])) => Vec3::new(x, y, z),
+            _ => {
+                return Err(type_error(
+                    "scale",
+                    "a number or an array of three numbers",
+                    value,
+                ));
+            }
+        };
+        if pose.scale.e.contains(&0.0) {
+            return Err(SceneError::invalid(
+                value.line,
+                "`scale` factors must not be zero",
+            ));
+        }
+    }
+    if let Some(angles) = vec3(t, "rotate")? {
+        pose.rotation = angles;
+    }
+    if let Some(offset) = vec3(t, "translate")? {
+        pose.translation = offset;
+    }
+    for (name, v) in [
+        ("scale", pose.scale),
+        ("rotate", pose.rotation),
+        ("translate", pose.translation),
+    ] {
+        if !v.e.iter().all(|x| x.is_finite()) {
+            return Err(SceneError::invalid(
+                t.get(name).map_or(t.line, |v| v.line),
+                format!("`{name}` must be finite, found {v}"),
+            ));
+        }
+    }
+    if pose.matrix().inverse().is_none() {
+        return Err(SceneError::invalid(
+            t.line,
+            format!("`{key}` can't be undone; is a `scale` too close to zero or too large?"),
+        ));
+    }
+    Ok(Some(pose))
+}
+
+fn load_camera(t: &Table, base: &Path) -> Result<CameraBuilder, SceneError> {
+    check_keys(
+        t,
+        "[camera]",
+        &[
+            "aspect_ratio",
+            "image_width",
+            "samples_per_pixel",
+            "max_depth",
+            "vfov",
+            "lookfrom",
+            "lookat",
+            "vup",
+            "defocus_angle",
+            "focus_dist",
+            "shutter_open",
+            "shutter_close",
+            "background",
+        ],
+    )?;
+
+    let mut camera = CameraBuilder::default();
+    if let Some(v) = positive(t, "aspect_ratio")? {
+        camera.aspect_ratio = v;
+    }
+    if let Some(v) = integer(t, "image_width", 1)? {
+        camera.image_width = v;
+    }
+    if let Some(v) = integer(t, "samples_per_pixel", 1)? {
+        camera.samples_per_pixel = v;
+    }
+    if let Some(v) = integer(t, "max_depth", 0)? {
+        camera.max_depth = v;
+    }
+    if let Some(v) = positive(t, "vfov")? {
+        if v >= 180.0 {
+            return Err(SceneError::invalid(
+                t.get("vfov").map_or(t.line, |v| v.line),
+                format!("`vfov` must be less than 180 degrees, found {v}"),
+            ));
+        }
+        camera.vfov = v;
+    }
+    if let Some(v) = vec3(t, "lookfrom")? {
+        camera.lookfrom = v;
+    }
+    if let Some(v) = vec3(t, "lookat")? {
+        camera.lookat = v;
+    }
+    if let Some(v) = vec3(t, "vup")? {
+        camera.vup = v;
+    }
+    if let Some(v) = non_negative(t, "defocus_angle")? {
+        camera.defocus_angle = v;
+    }
+    if let Some(v) = positive(t, "focus_dist")? {
+        camera.focus_dist = v;
+    }
+    if let Some(v) = unit_fraction(t, "shutter_open")? {
+        camera.shutter_open = v;
+    }
+    if let Some(v) = unit_fraction(t, "shutter_close")? {
+        camera.shutter_close = v;
+    }
+    if camera.shutter_close < camera.shutter_open {
+        return Err(SceneError::invalid(
+            t.get("shutter_close")
+                .or(t.get("shutter_open"))
+                .map_or(t.line, |v| v.line),
+            "`shutter_close` must not come before `shutter_open`",
+        ));
+    }
+    if let Some(v) = background(t, "background", base)? {
+        camera.background = v;
+    }
+    Ok(camera)
+}
+
+/// A plain color, or a `solid` (`color`), `gradient` (`bottom`, `top`) or
+/// `environment` (`path`, `rotation`, `intensity`) table.
+fn background(t: &Table, key: &str, base: &Path) -> Result<Option<Background>, SceneError> {
+    let Some(value) = t.get(key) else {
+        return Ok(None);
+    };
+    let definition = match &value.kind {
+        ValueKind::Array(_) => return Ok(color(t, key)?.map(Background::Solid)),
+        ValueKind::Table(definition) => definition,
+        _ => return Err(type_error(key, "a color or table", value)),
+    };
+
+    let kind = require(string(definition, "type")?, definition, "type")?;
+    match kind {
+        "solid" => {
+            check_keys(definition, "a solid background", &["type", "color"])?;
+            let color = require(color(definition, "color")?, definition, "color")?;
+            Ok(Some(Background::Solid(color)))
+        }
+        "gradient" => {
+            check_keys(
+                definition,
+                "a gradient background",
+                &["type", "bottom", "top"],
+            )?;
+            let bottom = require(color(definition, "bottom")?, definition, "bottom")?;
+            let top = require(color(definition, "top")?, definition, "top")?;
+            Ok(Some(Background::Gradient { bottom, top }))
+        }
+        "environment" => {
+            check_keys(
+                definition,
+                "an environment background",
+                &["type", "path", "rotation", "intensity"],
+            )?;
+            let file = require(string(definition, "path")?, definition, "path")?;
+            let rotation = number(definition, "rotation")?.unwrap_or(0.0);
+            let intensity = non_negative(definition, "intensity")?.unwrap_or(1.0);
+            let map = EnvironmentMap::load(base.join(file)).map_err(|err| {
+                SceneError::invalid(
+                    definition.get("path").map_or(definition.line, |v| v.line),
+                    format!("cannot load `{file}`: {err}"),
+                )
+            })?;
+            Ok(Some(Background::Environment(Arc::new(
+                map.with_rotation(rotation).with_intensity(intensity),
+            ))))
+        }
+        _ => Err(SceneError::invalid(
+            definition.get("type").map_or(definition.line, |v| v.line),
+            format!(
+                "unknown background type `{kind}` (expected `solid`, `gradient` or `environment`)"
+            ),
+        )),
+    }
+}
+
+/// Named textures and materials defined so far, and the directory that
+/// relative file paths are resolved against.
+struct Definitions<'a> {
+    base: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+}
+
+fn load_material(t: &Table, defs: &Definitions) -> Result<Arc<dyn Material>, SceneError> {
+    let kind = require(string(t, "type")?, t, "type")?;
+    match kind {
+        "lambertian" => {
+            check_keys(t, "a lambertian material", &["type", "albedo"])?;
+            let albedo = require(texture(t, "albedo", defs)?, t, "albedo")?;
+            Ok(Arc::new(Lambertian::from_texture(albedo)))
+        }
+        "metal" => {
+            check_keys(t, "a metal material", &["type", "albedo", "fuzz"])?;
+            let albedo = require(texture(t, "albedo", defs)?, t, "albedo")?;
+            let fuzz = non_negative(t, "fuzz")?.unwrap_or(0.0);
+            Ok(Arc::new(Metal::from_texture(albedo, fuzz)))
+        }
+        "dielectric" => {
+            check_keys(t, "a dielectric material", &["type", "refraction_index"])?;
+            let refraction_index =
+                require(positive(t, "refraction_index")?, t, "refraction_index")?;
+            Ok(Arc::new(Dielectric::new(refraction_index)))
+        }
+        "diffuse_light" => {
+            check_keys(t, "a diffuse_light material", &["type", "emit"])?;
+            let emit = require(texture(t, "emit", defs)?, t, "emit")?;
+            Ok(Arc::new(DiffuseLight::from_texture(emit)))
+        }
+        _ => Err(SceneError::invalid(
+            t.get("type").map_or(t.line, |v| v.line),
+            format!(
+                "unknown material type `{kind}` (expected `lambertian`, `metal`, `dielectric` \
+                 or `diffuse_light`)"
+            ),
+        )),
+    }
+}
+
+/// Resolves an object's `material`, which is either the name of an entry in
+/// `[materials]` or an inline material table.
+fn object_material(
+    object: &Table,
+    defs: &Definitions,
+) -> Result<Option<Arc<dyn Material>>, SceneError> {
+    let Some(value) = object.get("material") else {
+        return Ok(None);
+    };
+    match &value.kind {
+        ValueKind::String(name) => match defs.materials.get(name) {
+            Some(mat) => Ok(Some(mat.clone())),
+            None => Err(SceneError::invalid(
+                value.line,
+                format!("unknown material `{name}`"),
+            )),
+        },
+        ValueKind::Table(definition) => load_material(definition, defs).map(Some),
+        _ => Err(type_error("material", "a material name or table", value)),
+    }
+}
+
+fn load_texture(t: &Table, defs: &Definitions) -> Result<Arc<dyn Texture>, SceneError> {
+    let kind = require(string(t, "type")?, t, "type")?;
+    match kind {
+        "solid" => {
+            check_keys(t, "a solid texture", &["type", "color"])?;
+            let color = require(color(t, "color")?, t, "color")?;
+            Ok(Arc::new(SolidColor::new(&color)))
+        }
+        "checker" => {
+            check_keys(t, "a checker texture", &["type", "scale", "even", "odd"])?;
+            let scale = positive(t, "scale")?.unwrap_or(1.0);
+            let even = require(texture(t, "even", defs)?, t, "even")?;
+            let odd = require(texture(t, "odd", defs)?, t, "odd")?;
+            Ok(Arc::new(CheckerTexture::new(scale, even, odd)))
+        }
+        "noise" => {
+            check_keys(t, "a noise texture", &["type", "scale", "octaves", "seed"])?;
+            let seed = integer(t, "seed", 0)?.unwrap_or(0) as u64;
+            let scale = positive(t, "scale")?.unwrap_or(1.0);
+            let octaves = integer(t, "octaves", 1)?.unwrap_or(1);
+            Ok(Arc::new(
+                NoiseTexture::new(seed, scale).with_octaves(octaves),
+            ))
+        }
+        "marble" => {
+            check_keys(
+                t,
+                "a marble texture",
+                &[
+                    "type",
+                    "scale",
+                    "turbulence",
+                    "octaves",
+                    "seed",
+                    "base",
+                    "vein",
+                ],
+            )?;
+            let seed = integer(t, "seed", 0)?.unwrap_or(0) as u64;
+            let mut marble = MarbleTexture::new(seed, positive(t, "scale")?.unwrap_or(1.0));
+            if let Some(strength) = non_negative(t, "turbulence")? {
+                marble = marble.with_turbulence(strength);
+            }
+            if let Some(octaves) = integer(t, "octaves", 1)? {
+                marble = marble.with_octaves(octaves);
+            }
+            if let Some([base, vein]) = color_pair(t, ["base", "vein"])? {
+                marble = marble.with_colors(&base, &vein);
+            }
+            Ok(Arc::new(marble))
+        }
+        "wood" => {
+            check_keys(
+                t,
+                "a wood texture",
+                &[
+                    "type",
+                    "scale",
+                    "turbulence",
+                    "octaves",
+                    "seed",
+                    "light",
+                    "dark",
+                ],
+            )?;
+            let seed = integer(t, "seed", 0)?.unwrap_or(0) as u64;
+            let mut wood = WoodTexture::new(seed, positive(t, "scale")?.unwrap_or(1.0));
+            if let Some(strength) = non_negative(t, "turbulence")? {
+                wood = wood.with_turbulence(strength);
+            }
+            if let Some(octaves) = integer(t, "octaves", 1)? {
+                wood = wood.with_octaves(octaves);
+            }
+            if let Some([light, dark]) = color_pair(t, ["light", "dark"])? {
+                wood = wood.with_colors(&light, &dark);
+            }
+            Ok(Arc::new(wood))
+        }
+        "image" => {
+            check_keys(t, "an image texture", &["type", "path", "wrap", "filter"])?;
+            let file = require(string(t, "path")?, t, "path")?;
+            let wrap = match string(t, "wrap")? {
+                None | Some("repeat") => WrapMode::Repeat,
+                Some("mirror") => WrapMode::Mirror,
+                Some("clamp") => WrapMode::Clamp,
+                Some(other) => {
+                    return Err(SceneError::invalid(
+                        t.get("wrap").map_or(t.line, |v| v.line),
+                        format!(
+                            "unknown wrap mode `{other}` (expected `repeat`, `mirror` or `clamp`)"
+                        ),
+                    ));
+                }
+            };
+            let filter = match string(t, "filter")? {
+                None | Some("bilinear") => Filter::Bilinear,
+                Some("nearest") => Filter::Nearest,
+                Some(other) => {
+                    return Err(SceneError::invalid(
+                        t.get("filter").map_or(t.line, |v| v.line),
+                        format!("unknown filter `{other}` (expected `bilinear` or `nearest`)"),
+                    ));
+                }
+            };
+            let texture = ImageTexture::load(defs.base.join(file)).map_err(|err| {
+                SceneError::invalid(
+                    t.get("path").map_or(t.line, |v| v.line),
+                    format!("cannot load `{file}`: {err}"),
+                )
+            })?;
+            Ok(Arc::new(texture.with_wrap(wrap).with_filter(filter)))
+        }
+        _ => Err(SceneError::invalid(
+            t.get("type").map_or(t.line, |v| v.line),
+            format!(
+                "unknown texture type `{kind}` (expected `solid`, `checker`, `noise`, \
+                 `marble`, `wood` or `image`)"
+            ),
+        )),
+    }
+}
+
+/// A texture slot: a plain color, the name of an entry in `[textures]`, or an
+/// inline texture table.
+fn texture(
+    t: &Table,
+    key: &str,
+    defs: &Definitions,
+) -> Result<Option<Arc<dyn Texture>>, SceneError> {
+    let Some(value) = t.get(key) else {
+        return Ok(None);
+    };
+    match &value.kind {
+        ValueKind::Array(_) => {
+            let color = require(color(t, key)?, t, key)?;
+            Ok(Some(Arc::new(SolidColor::new(&color))))
+        }
+        ValueKind::String(name) => match defs.textures.get(name) {
+            Some(texture) => Ok(Some(texture.clone())),
+            None => Err(SceneError::invalid(
+                value.line,
+                format!("unknown texture `{name}`"),
+            )),
+        },
+        ValueKind::Table(definition) => load_texture(definition, defs).map(Some),
+        _ => Err(type_error(key, "a color, texture name or table", value)),
+    }
+}
+
+fn check_keys(t: &Table, context: &str, allowed: &[&str]) -> Result<(), SceneError> {
+    for (key, value) in t.iter() {
+        if !allowed.contains(&key) {
+            return Err(SceneError::invalid(
+                value.line,
+                format!(
+                    "unknown key `{key}` in {context} (expected one of: {})",
+                    allowed.join(", ")
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn require<T>(value: Option<T>, t: &Table, key: &str) -> Result<T, SceneError> {
+    value.ok_or_else(|| {
+        SceneError::invalid(
+            t.line,
+            format!("missing required key `{key}` in the table starting on this line"),
+        )
+    })
+}
+
+fn type_error(key: &str, expected: &str, value: &Value) -> SceneError {
+    let found = value.type_name();
+    let article = if found.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    };
+    SceneError::invalid(
+        value.line,
+        format!("`{key}` must be {expected}, found {article} {found}"),
+    )
+}
+
+/// A finite number; `nan` and `inf` are refused.
+fn number(t: &Table, key: &str) -> Result<Option<f64>, SceneError> {
+    match t.get(key) {
+        None => Ok(None),
+        Some(Value {
+            kind: ValueKind::Number(n),
+            line,
+        }) if !n.is_finite() => Err(SceneError::invalid(
+            *line,
+            format!("`{key}` must be a finite number, found {n}"),
+        )),
+        Some(Value {
+            kind: ValueKind::Number(n),
+            ..
+        }) => Ok(Some(*n)),
+        Some(value) => Err(type_error(key, "a number", value)),
+    }
+}
+
+fn positive(t: &Table, key: &str) -> Result<Option<f64>, SceneError> {
+    match number(t, key)? {
+        Some(n) if n <= 0.0 || !n.is_finite() => Err(SceneError::invalid(
+            t.get(key).map_or(t.line, |v| v.line),
+            format!("`{key}` must be greater than zero, found {n}"),
+        )),
+        n => Ok(n),
+    }
+}
+
+fn non_negative(t: &Table, key: &str) -> Result<Option<f64>, SceneError> {
+    match number(t, key)? {
+        Some(n) if n < 0.0 || !n.is_finite() => Err(SceneError::invalid(
+            t.get(key).map_or(t.line, |v| v.line),
+            format!("`{key}` must not be negative, found {n}"),
+        )),
+        n => Ok(n),
+    }
+}
+
+fn unit_fraction(t: &Table, key: &str) -> Result<Option<f64>, SceneError> {
+    match number(t, key)? {
+        Some(n) if !(0.0..=1.0).contains(&n) => Err(SceneError::invalid(
+            t.get(key).map_or(t.line, |v| v.line),
+            format!("`{key}` must be from 0 to 1, found {n}"),
+        )),
+        n => Ok(n),
+    }
+}
+
+fn integer(t: &Table, key: &str, min: u32) -> Result<Option<u32>, SceneError> {
+    match number(t, key)? {
+        Some(n) if n.fract() != 0.0 || n < min as f64 || n > u32::MAX as f64 => {
+            Err(SceneError::invalid(
+                t.get(key).map_or(t.line, |v| v.line),
+                format!("`{key}` must be a whole number of at least {min}, found {n}"),
+            ))
+        }
+        n => Ok(n.map(|n| n as u32)),
+    }
+}
+
+fn string<'t>(t: &'t Table, key: &str) -> Result<Option<&'t str>, SceneError> {
+    match t.get(key) {
+        None => Ok(None),
+        Some(Value {
+            kind: ValueKind::String(s),
+            ..
+        }) => Ok(Some(s)),
+        Some(value) => Err(type_error(key, "a string", value)),
+    }
+}
+
+fn table<'t>(t: &'t Table, key: &str) -> Result<Option<&'t Table>, SceneError> {
+    match t.get(key) {
+        None => Ok(None),
+        Some(Value {
+            kind: ValueKind::Table(inner),
+            ..
+        }) => Ok(Some(inner)),
+        Some(value) => Err(type_error(key, "a table", value)),
+    }
+}
+
+fn array<'t>(t: &'t Table, key: &str) -> Result<Option<&'t [Value]>, SceneError> {
+    match t.get(key) {
+        None => Ok(None),
+        Some(Value {
+            kind: ValueKind::Array(items),
+            ..
+        }) => Ok(Some(items)),
+        Some(value) => Err(type_error(key, "an array", value)),
+    }
+}
+
+/// The numbers of `value` if it is an array of exactly `N` numbers.
+fn fixed_numbers<const N: usize>(value: &Value) -> Option<[f64; N]> {
+    let ValueKind::Array(items) = &value.kind else {
+        return None;
+    };
+    if items.len() != N {
+        return None;
+    }
+    let mut numbers = [0.0; N];
+    for (n, item) in numbers.iter_mut().zip(items) {
+        let ValueKind::Number(x) = item.kind else {
+            return None;
+        };
+        *n = x;
+    }
+    Some(numbers)
+}
+
+fn vec3(t: &Table, key: &str) -> Result<Option<Vec3>, SceneError> {
+    let Some(value) = t.get(key) else {
+        return Ok(None);
+    };
+    match fixed_numbers::<3>(value) {
+        Some([x, y, z]) => Ok(Some(Vec3::new(x, y, z))),
+        None => Err(type_error(key, "an array of three numbers", value)),
+    }
+}
+
+/// An array whose elements are each arrays of `N` numbers, e.g. mesh vertices.
+fn tuples<const N: usize>(t: &Table, key: &str) -> Result<Option<Vec<[f64; N]>>, SceneError> {
+    let Some(items) = array(t, key)? else {
+        return Ok(None);
+    };
+    items
+        .iter()
+        .map(|item| {
+            fixed_numbers::<N>(item).ok_or_else(|| {
+                SceneError::invalid(
+                    item.line,
+                    format!("every entry of `{key}` must be an array of {N} numbers"),
+                )
+            })
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+fn color(t: &Table, key: &str) -> Result<Option<Color>, SceneError> {
+    Ok(vec3(t, key)?.map(Color))
+}
+
+/// Two colors that must be given together, such as a texture's palette.
+fn color_pair(t: &Table, keys: [&str; 2]) -> Result<Option<[Color; 2]>, SceneError> {
+    match (color(t, keys[0])?, color(t, keys[1])?) {
+        (Some(a), Some(b)) => Ok(Some([a, b])),
+        (None, None) => Ok(None),
+        _ => Err(SceneError::invalid(
+            t.line,
+            format!("`{}` and `{}` must be given together", keys[0], keys[1]),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line and message of the error `source` fails to load with.
+    fn error(source: &str) -> (usize, String) {
+        match Scene::parse(source) {
+            Err(SceneError::Invalid { line, message }) => (line, message),
+            Err(SceneError::Io(err)) => panic!("unexpected I/O error: {err}"),
+            Ok(_) => panic!("scene loaded:\n{source}"),
+        }
+    }
+
+    #[test]
+    fn transforms_must_be_finite_and_invertible() {
+        let sphere = "[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\n\
+                      material = { type = \"lambertian\", albedo = [0.5, 0.5, 0.5] }\n";
+        for (transform, line, expected) in [
+            (
+                "transform = { scale = 1e-150 }",
+                6,
+                "`transform` can't be undone",
+            ),
+            (
+                "transform = { scale = 1e200 }",
+                6,
+                "`transform` can't be undone",
+            ),
+            ("transform = { scale = nan }", 6, "`scale` must be finite"),
+            (
+                "transform = { rotate = [0, inf, 0] }",
+                6,
+                "`rotate` must be finite",
+            ),
+            (
+                "transform_end = { translate = [nan, 0, 0] }",
+                6,
+                "`translate` must be finite",
+            ),
+            ("transform = { scale = [1, 0, 1] }", 6, "must not be zero"),
+        ] {
+            let (found_line, message) = error(&format!("{sphere}{transform}\n"));
+            assert_eq!(found_line, line, "{transform}");
+            assert!(message.contains(expected), "{message}");
+        }
+        Scene::parse(&format!("{sphere}transform = {{ scale = 1e-100 }}\n")).unwrap();
+    }
+
+    #[test]
+    fn numbers_must_be_finite_and_in_range() {
+        for (source, expected) in [
+            ("[camera]\nvfov = nan\n", "`vfov` must be a finite number"),
+            ("[camera]\nvfov = inf\n", "`vfov` must be a finite number"),
+            (
+                "[camera]\nvfov = 180\n",
+                "`vfov` must be less than 180 degrees",
+            ),
+            ("[camera]\nvfov = 0\n", "`vfov` must be greater than zero"),
+            (
+                "[camera]\ndefocus_angle = -1\n",
+                "`defocus_angle` must not be negative",
+            ),
+            (
+                "[camera]\nfocus_dist = -inf\n",
+                "`focus_dist` must be a finite number",
+            ),
+            (
+                "[materials]\nm = { type = \"metal\", albedo = [1, 1, 1], fuzz = -0.5 }\n",
+                "`fuzz` must not be negative",
+            ),
+            (
+                "[materials]\nm = { type = \"lambertian\", \
+                 albedo = { type = \"marble\", turbulence = -2 } }\n",
+                "`turbulence` must not be negative",
+            ),
+        ] {
+            let (line, message) = error(source);
+            assert_eq!(line, 2, "{source:?}");
+            assert!(message.contains(expected), "{message}");
+        }
+        let scene = Scene::parse("[camera]\nvfov = 179.5\ndefocus_angle = 0\n").unwrap();
+        assert_eq!(scene.camera.vfov, 179.5);
+    }
+
+    #[test]
+    fn shutter_times_must_be_within_the_exposure() {
+        let scene = Scene::parse("[camera]\nshutter_open = 0.25\nshutter_close = 0.5\n").unwrap();
+        assert_eq!(scene.camera.shutter_open, 0.25);
+        assert_eq!(scene.camera.shutter_close, 0.5);
+
+        for (source, line, expected) in [
+            (
+                "[camera]\nshutter_open = -0.5\n",
+                2,
+                "`shutter_open` must be from 0 to 1",
+            ),
+            (
+                "[camera]\n\nshutter_close = 1.5\n",
+                3,
+                "`shutter_close` must be from 0 to 1",
+            ),
+            (
+                "[camera]\nshutter_close = nan\n",
+                2,
+                "`shutter_close` must be a finite number",
+            ),
+            (
+                "[camera]\nshutter_open = inf\n",
+                2,
+                "`shutter_open` must be a finite number",
+            ),
+            (
+                "[camera]\nshutter_open = 0.5\nshutter_close = 0.25\n",
+                3,
+                "`shutter_close` must not come before `shutter_open`",
+            ),
+        ] {
+            let (found_line, message) = error(source);
+            assert_eq!(found_line, line, "{source:?}");
+            assert!(message.contains(expected), "{message}");
+        }
+    }
+
+    #[test]
+    fn grid_resolution_is_capped() {
+        let grid = |resolution: &str| {
+            format!(
+                "[[objects]]\ntype = \"grid_medium\"\nmin = [0, 0, 0]\nmax = [1, 1, 1]\n\
+                 density = {{ type = \"noise\", resolution = {resolution} }}\n"
+            )
+        };
+        assert!(Scene::parse(&grid("[2, 3, 4]")).is_ok());
+
+        let (line, message) = error(&grid("[4096, 4096, 4096]"));
+        assert_eq!(line, 5);
+        assert!(message.contains("at most 134217728 voxels"), "{message}");
+
+        let (_, message) = error(&grid("[0, 4, 4]"));
+        assert!(message.contains("from 1 to 4096"), "{message}");
+    }
+
+    #[test]
+    fn backgrounds_are_colors_or_tables() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let camera = |background: &str| {
+            Scene::parse(&format!("[camera]\nbackground = {background}\n"))
+                .unwrap()
+                .camera
+        };
+
+        let solid = camera("[0.1, 0.2, 0.3]");
+        assert_eq!(solid.background.value(&up).0.e, [0.1, 0.2, 0.3]);
+        let solid = camera("{ type = \"solid\", color = [0, 0, 0] }");
+        assert_eq!(solid.background.value(&down).0.e, [0.0; 3]);
+        let gradient = camera("{ type = \"gradient\", bottom = [1, 0, 0], top = [0, 0, 1] }");
+        assert_eq!(gradient.background.value(&up).0.e, [0.0, 0.0, 1.0]);
+        assert_eq!(gradient.background.value(&down).0.e, [1.0, 0.0, 0.0]);
+        // Left out, it is the white-to-blue sky.
+        let sky = Scene::parse("").unwrap().camera;
+        assert_eq!(sky.background.value(&up).0.e, [0.5, 0.7, 1.0]);
+
+        for (background, expected) in [
+            ("{ type = \"plaid\" }", "unknown background type `plaid`"),
+            ("{ type = \"gradient\", top = [0, 0, 1] }", "`bottom`"),
+            ("\"blue\"", "`background` must be a color or table"),
+        ] {
+            let (line, message) = error(&format!("[camera]\nbackground = {background}\n"));
+            assert_eq!(line, 2);
+            assert!(message.contains(expected), "{message}");
+        }
+    }
+
+    #[test]
+    fn grid_media_need_a_volume() {
+        let (line, message) = error(
+            "[[objects]]\ntype = \"grid_medium\"\nmin = [0, 0, 0]\nmax = [1, 0, 1]\n\
+             density = { type = \"noise\", resolution = [2, 2, 2] }\n",
+        );
+        assert_eq!(line, 4);
+        assert!(
+            message.contains("must differ along every axis"),
+            "{message}"
+        );
+    }
+}
@@ -0,0 +1,512 @@
+//! Built-in scenes that can be rendered without a scene file.
+
+use std::sync::Arc;
+
+use crate::{
+    background::Background,
+    camera::CameraBuilder,
+    color::Color,
+    constant_medium::ConstantMedium,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    quad::{Quad, make_box},
+    rtweekend::{random_f64, random_f64_within},
+    scene::Scene,
+    sphere::Sphere,
+    texture::{CheckerTexture, MarbleTexture, NoiseTexture, WoodTexture},
+    transform::{Matrix4, Transform},
+    vec3::{Point3, Vec3},
+};
+
+/// Names accepted by `preset`.
+pub const PRESETS: &[&str] = &[
+    "random-spheres",
+    "bouncing-spheres",
+    "checkered-spheres",
+    "perlin-spheres",
+    "quads",
+    "simple-light",
+    "cornell-box",
+    "cornell-smoke",
+];
+
+pub fn preset(name: &str) -> Option<Scene> {
+    match name {
+        "random-spheres" => Some(random_spheres()),
+        "bouncing-spheres" => Some(bouncing_spheres()),
+        "checkered-spheres" => Some(checkered_spheres()),
+        "perlin-spheres" => Some(perlin_spheres()),
+        "quads" => Some(quads()),
+        "simple-light" => Some(simple_light()),
+        "cornell-box" => Some(cornell_box()),
+        "cornell-smoke" => Some(cornell_smoke()),
+        _ => None,
+    }
+}
+
+/// The cover of "Ray Tracing in One Weekend": a field of small random spheres
+/// around three large ones.
+pub fn random_spheres() -> Scene {
+    sphere_field(false)
+}
+
+/// The random spheres with the small diffuse ones hopping upward while the
+/// shutter is open, blurring them.
+pub fn bouncing_spheres() -> Scene {
+    sphere_field(true)
+}
+
+fn sphere_field(bouncing: bool) -> Scene {
+    let mut world: HittableList = HittableList::default();
+
+    let material_ground: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color(Vec3::new(0.5, 0.5, 0.5)))));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        material_ground,
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random_f64();
+            let center = Point3::new(
+                a as f64 + 0.9 * random_f64(),
+                0.2,
+                b as f64 + 0.9 * random_f64(),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let sphere_material: Option<Arc<dyn Material>> = {
+                    if choose_mat < 0.8 {
+                        let albedo = Color(Vec3::random()) * Color(Vec3::random());
+                        Some(Arc::new(Lambertian::new(&albedo)))
+                    } else if choose_mat < 0.95 {
+                        let albedo = Color(Vec3::random_within(0.5, 1.0));
+                        let fuzz = random_f64_within(0.0, 0.5);
+                        Some(Arc::new(Metal::new(&albedo, fuzz)))
+                    } else {
+                        Some(Arc::new(Dielectric::new(1.5)))
+                    }
+                };
+
+                let center_end = if bouncing && choose_mat < 0.8 {
+                    center + Vec3::new(0.0, random_f64_within(0.0, 0.5), 0.0)
+                } else {
+                    center
+                };
+                world.add(Arc::new(Sphere::moving(
+                    &center,
+                    &center_end,
+                    0.2,
+                    sphere_material,
+                )));
+            }
+        }
+    }
+
+    let material1: Option<Arc<dyn Material>> = Some(Arc::new(Dielectric::new(1.50)));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        material1,
+    )));
+
+    let material2: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color(Vec3::new(0.4, 0.2, 0.1)))));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        material2,
+    )));
+
+    let material3: Option<Arc<dyn Material>> =
+        Some(Arc::new(Metal::new(&Color(Vec3::new(0.7, 0.6, 0.5)), 0.0)));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        material3,
+    )));
+
+    let camera = CameraBuilder::default()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(1200)
+        .samples_per_pixel(500)
+        .max_depth(50)
+        .vfov(20.0)
+        .lookfrom(&Point3::new(13.0, 2.0, 3.0))
+        .lookat(&Point3::new(0.0, 0.0, 0.0))
+        .vup(&Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.6)
+        .focus_dist(10.0);
+
+    Scene {
+        camera,
+        world,
+        lights: HittableList::default(),
+    }
+}
+
+/// Two large spheres sharing one 3D checker texture.
+pub fn checkered_spheres() -> Scene {
+    let mut world: HittableList = HittableList::default();
+
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.32,
+        &Color::new(0.2, 0.3, 0.1),
+        &Color::new(0.9, 0.9, 0.9),
+    ));
+    let material: Option<Arc<dyn Material>> = Some(Arc::new(Lambertian::from_texture(checker)));
+
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, -10.0, 0.0),
+        10.0,
+        material.clone(),
+    )));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 10.0, 0.0),
+        10.0,
+        material,
+    )));
+
+    let camera = CameraBuilder::default()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .lookfrom(&Point3::new(13.0, 2.0, 3.0))
+        .lookat(&Point3::new(0.0, 0.0, 0.0))
+        .vup(&Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.0);
+
+    Scene {
+        camera,
+        world,
+        lights: HittableList::default(),
+    }
+}
+
+/// Marble, wood and fractal noise spheres on a turbulent noise ground.
+pub fn perlin_spheres() -> Scene {
+    let mut world: HittableList = HittableList::default();
+
+    let ground = Arc::new(NoiseTexture::new(7, 4.0).with_octaves(6));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Some(Arc::new(Lambertian::from_texture(ground))),
+    )));
+
+    let marble = Arc::new(MarbleTexture::new(1, 4.0));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Some(Arc::new(Lambertian::from_texture(marble))),
+    )));
+
+    let wood = Arc::new(WoodTexture::new(2, 8.0));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 1.0, 3.5),
+        1.0,
+        Some(Arc::new(Lambertian::from_texture(wood))),
+    )));
+
+    let noise = Arc::new(NoiseTexture::new(3, 6.0));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 1.0, -3.5),
+        1.0,
+        Some(Arc::new(Metal::from_texture(noise, 0.2))),
+    )));
+
+    let camera = CameraBuilder::default()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .lookfrom(&Point3::new(13.0, 2.0, 3.0))
+        .lookat(&Point3::new(0.0, 1.0, 0.0))
+        .vup(&Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.0);
+
+    Scene {
+        camera,
+        world,
+        lights: HittableList::default(),
+    }
+}
+
+/// Five colored quads facing the camera from different sides.
+pub fn quads() -> Scene {
+    let mut world: HittableList = HittableList::default();
+
+    let left_red: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(1.0, 0.2, 0.2))));
+    let back_green: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.2, 1.0, 0.2))));
+    let right_blue: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.2, 0.2, 1.0))));
+    let upper_orange: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(1.0, 0.5, 0.0))));
+    let lower_teal: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.2, 0.8, 0.8))));
+
+    world.add(Arc::new(Quad::new(
+        &Point3::new(-3.0, -2.0, 5.0),
+        &Vec3::new(0.0, 0.0, -4.0),
+        &Vec3::new(0.0, 4.0, 0.0),
+        left_red,
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(-2.0, -2.0, 0.0),
+        &Vec3::new(4.0, 0.0, 0.0),
+        &Vec3::new(0.0, 4.0, 0.0),
+        back_green,
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(3.0, -2.0, 1.0),
+        &Vec3::new(0.0, 0.0, 4.0),
+        &Vec3::new(0.0, 4.0, 0.0),
+        right_blue,
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(-2.0, 3.0, 1.0),
+        &Vec3::new(4.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 4.0),
+        upper_orange,
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(-2.0, -3.0, 5.0),
+        &Vec3::new(4.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -4.0),
+        lower_teal,
+    )));
+
+    let camera = CameraBuilder::default()
+        .aspect_ratio(1.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(80.0)
+        .lookfrom(&Point3::new(0.0, 0.0, 9.0))
+        .lookat(&Point3::new(0.0, 0.0, 0.0))
+        .vup(&Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.0);
+
+    Scene {
+        camera,
+        world,
+        lights: HittableList::default(),
+    }
+}
+
+/// A marble sphere on marble ground, lit by a rectangular lamp and a
+/// glowing sphere against a black sky.
+pub fn simple_light() -> Scene {
+    let mut world: HittableList = HittableList::default();
+
+    let marble: Option<Arc<dyn Material>> = Some(Arc::new(Lambertian::from_texture(Arc::new(
+        MarbleTexture::new(0, 4.0),
+    ))));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        marble.clone(),
+    )));
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        marble,
+    )));
+
+    let light: Option<Arc<dyn Material>> =
+        Some(Arc::new(DiffuseLight::new(&Color::new(4.0, 4.0, 4.0))));
+    let mut lights: HittableList = HittableList::default();
+    lights.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 7.0, 0.0),
+        2.0,
+        light.clone(),
+    )));
+    lights.add(Arc::new(Quad::new(
+        &Point3::new(3.0, 1.0, -2.0),
+        &Vec3::new(2.0, 0.0, 0.0),
+        &Vec3::new(0.0, 2.0, 0.0),
+        light,
+    )));
+    for lamp in lights.objects() {
+        world.add(lamp.clone());
+    }
+
+    let camera = CameraBuilder::default()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .background(Background::Solid(Color::default()))
+        .vfov(20.0)
+        .lookfrom(&Point3::new(26.0, 3.0, 6.0))
+        .lookat(&Point3::new(0.0, 2.0, 0.0))
+        .vup(&Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.0);
+
+    Scene {
+        camera,
+        world,
+        lights,
+    }
+}
+
+/// The Cornell box: a white room with a red and a green wall, lit only by a
+/// ceiling lamp, holding two white boxes turned to face different ways.
+pub fn cornell_box() -> Scene {
+    let white: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73))));
+    let light: Option<Arc<dyn Material>> =
+        Some(Arc::new(DiffuseLight::new(&Color::new(15.0, 15.0, 15.0))));
+
+    let mut world = cornell_room(white.clone());
+    let lamp: Arc<dyn Hittable> = Arc::new(Quad::new(
+        &Point3::new(343.0, 554.0, 332.0),
+        &Vec3::new(-130.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -105.0),
+        light,
+    ));
+    world.add(lamp.clone());
+
+    let [tall, short] = cornell_blocks(white);
+    world.add(tall);
+    world.add(short);
+
+    Scene {
+        camera: cornell_camera(),
+        world,
+        lights: HittableList::new(lamp),
+    }
+}
+
+/// The Cornell box with its two blocks turned into dark and light smoke,
+/// under a larger, dimmer lamp.
+pub fn cornell_smoke() -> Scene {
+    let white: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73))));
+    let light: Option<Arc<dyn Material>> =
+        Some(Arc::new(DiffuseLight::new(&Color::new(7.0, 7.0, 7.0))));
+
+    let mut world = cornell_room(white.clone());
+    let lamp: Arc<dyn Hittable> = Arc::new(Quad::new(
+        &Point3::new(113.0, 554.0, 127.0),
+        &Vec3::new(330.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 305.0),
+        light,
+    ));
+    world.add(lamp.clone());
+
+    let [tall, short] = cornell_blocks(white);
+    world.add(Arc::new(ConstantMedium::new(
+        tall,
+        0.01,
+        &Color::new(0.0, 0.0, 0.0),
+    )));
+    world.add(Arc::new(ConstantMedium::new(
+        short,
+        0.01,
+        &Color::new(1.0, 1.0, 1.0),
+    )));
+
+    Scene {
+        camera: cornell_camera(),
+        world,
+        lights: HittableList::new(lamp),
+    }
+}
+
+/// The five walls of the Cornell box, without a lamp.
+fn cornell_room(white: Option<Arc<dyn Material>>) -> HittableList {
+    let mut world: HittableList = HittableList::default();
+
+    let red: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05))));
+    let green: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::new(0.12, 0.45, 0.15))));
+
+    world.add(Arc::new(Quad::new(
+        &Point3::new(555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 555.0, 0.0),
+        &Vec3::new(0.0, 0.0, 555.0),
+        green,
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vec3::new(0.0, 555.0, 0.0),
+        &Vec3::new(0.0, 0.0, 555.0),
+        red,
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vec3::new(555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(555.0, 555.0, 555.0),
+        &Vec3::new(-555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Point3::new(0.0, 0.0, 555.0),
+        &Vec3::new(555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 555.0, 0.0),
+        white,
+    )));
+
+    world
+}
+
+/// The tall and the short block, turned and placed in the room.
+fn cornell_blocks(mat: Option<Arc<dyn Material>>) -> [Arc<dyn Hittable>; 2] {
+    let tall = make_box(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(165.0, 330.0, 165.0),
+        mat.clone(),
+    );
+    let short = make_box(
+        &Point3::new(0.0, 0.0, 0.0),
+        &Point3::new(165.0, 165.0, 165.0),
+        mat,
+    );
+    [
+        Arc::new(
+            Transform::new(
+                Arc::new(tall),
+                Matrix4::rotation_y(15.0)
+                    .then(&Matrix4::translation(&Vec3::new(265.0, 0.0, 295.0))),
+            )
+            .expect("rotations and translations are invertible"),
+        ),
+        Arc::new(
+            Transform::new(
+                Arc::new(short),
+                Matrix4::rotation_y(-18.0)
+                    .then(&Matrix4::translation(&Vec3::new(130.0, 0.0, 65.0))),
+            )
+            .expect("rotations and translations are invertible"),
+        ),
+    ]
+}
+
+fn cornell_camera() -> CameraBuilder {
+    CameraBuilder::default()
+        .aspect_ratio(1.0)
+        .image_width(600)
+        .samples_per_pixel(200)
+        .max_depth(50)
+        .background(Background::Solid(Color::default()))
+        .vfov(40.0)
+        .lookfrom(&Point3::new(278.0, 278.0, -800.0))
+        .lookat(&Point3::new(278.0, 278.0, 0.0))
+        .vup(&Vec3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.0)
+}
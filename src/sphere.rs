@@ -1,23 +1,30 @@
-use std::rc::Rc;
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     interval::Interval,
     material::Material,
+    onb::Onb,
     ray::Ray,
-    vec3::{Point3, dot},
+    rtweekend::random_f64,
+    vec3::{Point3, Vec3, dot, random_unit_vector},
 };
 
 #[derive(Default)]
 pub struct Sphere {
-    center: Point3,
+    /// Traces the center from time 0 to time 1; a still sphere's has no
+    /// direction.
+    center: Ray,
     radius: f64,
-    mat: Option<Rc<dyn Material>>,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let oc = self.center - *r.origin();
+        let current_center = self.center.at(r.time().clamp(0.0, 1.0));
+        let oc = current_center - *r.origin();
         let a = r.direction().length_squared();
         let h = dot(r.direction(), &oc);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -38,20 +45,144 @@ impl Hittable for Sphere {
         let mut rec = HitRecord::default();
         rec.t = root;
         rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - self.center) / self.radius;
+        let outward_normal = (rec.p - current_center) / self.radius;
         rec.set_face_normal(r, &outward_normal);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(&outward_normal);
         rec.mat = self.mat.clone();
 
-        return Some(rec);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        if self.hit(r, Interval::new(0.001, f64::INFINITY)).is_none() {
+            return 0.0;
+        }
+        let center = self.center.at(r.time().clamp(0.0, 1.0));
+        match self.cone(&(center - *r.origin())) {
+            Some(one_minus_cos_theta_max) => 1.0 / (2.0 * PI * one_minus_cos_theta_max),
+            None => 1.0 / (4.0 * PI),
+        }
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let direction = self.center.at(time.clamp(0.0, 1.0)) - *origin;
+        let Some(one_minus_cos_theta_max) = self.cone(&direction) else {
+            return random_unit_vector();
+        };
+
+        // A direction uniformly within the cone the sphere fills, about +Z.
+        let r1 = random_f64();
+        let r2 = random_f64();
+        let z = 1.0 - r2 * one_minus_cos_theta_max;
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        let in_cone = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z);
+        Onb::new(&direction).transform(&in_cone)
     }
 }
 
 impl Sphere {
-    pub fn new(center: &Point3, radius: f64, mat: Option<Rc<dyn Material>>) -> Self {
+    pub fn new(center: &Point3, radius: f64, mat: Option<Arc<dyn Material>>) -> Self {
+        Self::moving(center, center, radius, mat)
+    }
+
+    /// A sphere moving in a straight line from `center0` at time 0 to
+    /// `center1` at time 1, and resting at those ends outside that range.
+    pub fn moving(
+        center0: &Point3,
+        center1: &Point3,
+        radius: f64,
+        mat: Option<Arc<dyn Material>>,
+    ) -> Self {
+        let radius = radius.max(0.0);
+        let rvec = Vec3::new(radius, radius, radius);
+        let box0 = Aabb::from_points(&(*center0 - rvec), &(*center0 + rvec));
+        let box1 = Aabb::from_points(&(*center1 - rvec), &(*center1 + rvec));
         Self {
-            center: center.clone(),
-            radius: radius.max(0.0),
+            center: Ray::new(center0, &(*center1 - *center0)),
+            radius,
             mat,
+            bbox: Aabb::from_boxes(&box0, &box1),
+        }
+    }
+
+    /// `1 - cos(theta_max)` for the cone of directions in which the sphere
+    /// is seen along `to_center`, or `None` from inside it, where every
+    /// direction hits it.
+    fn cone(&self, to_center: &Vec3) -> Option<f64> {
+        let distance_squared = to_center.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return None;
         }
+        // Written to avoid cancellation for small or distant spheres.
+        let sin2_theta_max = self.radius * self.radius / distance_squared;
+        Some(sin2_theta_max / (1.0 + (1.0 - sin2_theta_max).sqrt()))
+    }
+
+    /// Maps a point on the unit sphere to `u` (angle around the Y axis from
+    /// X=-1, over 2π) and `v` (angle from Y=-1 up to Y=+1, over π).
+    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_uv(p: [f64; 3], expected: (f64, f64)) {
+        let (u, v) = Sphere::get_sphere_uv(&Point3::new(p[0], p[1], p[2]));
+        assert!(
+            (u - expected.0).abs() < 1e-9 && (v - expected.1).abs() < 1e-9,
+            "{p:?}: ({u}, {v}) vs {expected:?}"
+        );
+    }
+
+    #[test]
+    fn uv_runs_around_y_from_minus_x() {
+        assert_uv([-1.0, 0.0, 0.0], (0.0, 0.5));
+        assert_uv([0.0, 0.0, 1.0], (0.25, 0.5));
+        assert_uv([1.0, 0.0, 0.0], (0.5, 0.5));
+        assert_uv([0.0, 0.0, -1.0], (0.75, 0.5));
+    }
+
+    #[test]
+    fn uv_at_the_poles_and_the_seam() {
+        assert_uv([0.0, -1.0, 0.0], (0.5, 0.0));
+        assert_uv([0.0, 1.0, 0.0], (0.5, 1.0));
+        // Rounding can leave a normal just past a pole.
+        assert_uv([0.0, 1.0 + 1e-12, 0.0], (0.5, 1.0));
+        assert_uv([0.0, -1.0 - 1e-12, 0.0], (0.5, 0.0));
+
+        // Either side of the seam at X = -1, u wraps from 1 to 0.
+        let (before, _) = Sphere::get_sphere_uv(&Point3::new(-1.0, 0.0, -1e-9));
+        let (after, _) = Sphere::get_sphere_uv(&Point3::new(-1.0, 0.0, 1e-9));
+        assert!((1.0 - 1e-6..=1.0).contains(&before), "{before}");
+        assert!((0.0..1e-6).contains(&after), "{after}");
+    }
+
+    #[test]
+    fn hits_carry_the_uv_of_the_outward_normal() {
+        let sphere = Sphere::new(&Point3::new(0.0, 5.0, 0.0), 2.0, None);
+        let from_above = Ray::new(&Point3::new(0.0, 10.0, 0.0), &Vec3::new(0.0, -1.0, 0.0));
+        let rec = sphere
+            .hit(&from_above, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-12);
+        assert!((rec.v - 1.0).abs() < 1e-9);
+
+        // From inside, the UV still comes from the outward side.
+        let from_inside = Ray::new(&Point3::new(0.0, 5.0, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        let rec = sphere
+            .hit(&from_inside, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!(!rec.front_face);
+        assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
     }
 }
@@ -0,0 +1,363 @@
+use std::{f64::consts::PI, io, path::Path, sync::Arc};
+
+use crate::{
+    color::Color,
+    image::Image,
+    perlin::Perlin,
+    vec3::{Point3, Vec3},
+};
+
+/// A color that varies over a surface, looked up by the hit's surface
+/// coordinates `u`/`v` or by its position `p`.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: &Color) -> Self {
+        Self { albedo: *albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// Alternates between two textures on a grid of cubes `scale` units wide, so
+/// the pattern is continuous across any surface cutting through it.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: &Color, odd: &Color) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// How texture coordinates outside 0..=1 are brought back onto the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Tile the image.
+    #[default]
+    Repeat,
+    /// Tile the image, flipping every other copy so edges meet seamlessly.
+    Mirror,
+    /// Extend the edge pixels.
+    Clamp,
+}
+
+impl WrapMode {
+    /// Maps pixel index `i` into `0..n`.
+    fn apply(self, i: i64, n: i64) -> i64 {
+        match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m < n { m } else { 2 * n - 1 - m }
+            }
+            WrapMode::Clamp => i.clamp(0, n - 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// The single closest pixel.
+    Nearest,
+    /// A weighted blend of the four closest pixels.
+    #[default]
+    Bilinear,
+}
+
+/// An image mapped onto a surface by its `u`/`v` coordinates, with `v = 0`
+/// at the bottom row.
+pub struct ImageTexture {
+    image: Image,
+    wrap_u: WrapMode,
+    wrap_v: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            wrap_u: WrapMode::default(),
+            wrap_v: WrapMode::default(),
+            filter: Filter::default(),
+        }
+    }
+
+    /// Loads a PPM or PNG file, decoding its sRGB colors to linear.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(Image::load(path)?))
+    }
+
+    /// Sets the wrap mode of both axes.
+    pub fn with_wrap(self, wrap: WrapMode) -> Self {
+        self.with_wrap_uv(wrap, wrap)
+    }
+
+    pub fn with_wrap_uv(mut self, wrap_u: WrapMode, wrap_v: WrapMode) -> Self {
+        self.wrap_u = wrap_u;
+        self.wrap_v = wrap_v;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    fn texel(&self, i: i64, j: i64) -> Color {
+        let width = self.image.width() as i64;
+        let height = self.image.height() as i64;
+        let i = self.wrap_u.apply(i, width);
+        let j = self.wrap_v.apply(j, height);
+        self.image.pixel(i as u32, j as u32)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        if self.image.width() == 0 || self.image.height() == 0 {
+            // Cyan makes a missing image easy to spot.
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        // Continuous pixel coordinates, with rows counted from the top.
+        let x = u * self.image.width() as f64;
+        let y = (1.0 - v) * self.image.height() as f64;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // Pixel centers sit at half-integer coordinates.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (i0, j0) = (x.floor() as i64, y.floor() as i64);
+                let (i1, j1) = (i0.saturating_add(1), j0.saturating_add(1));
+                let (fx, fy) = (x - x.floor(), y - y.floor());
+
+                let top = Color((1.0 - fx) * self.texel(i0, j0).0 + fx * self.texel(i1, j0).0);
+                let bottom = Color((1.0 - fx) * self.texel(i0, j1).0 + fx * self.texel(i1, j1).0);
+                Color((1.0 - fy) * top.0 + fy * bottom.0)
+            }
+        }
+    }
+}
+
+/// Gray Perlin noise. With more than one octave the noise is layered into
+/// fractal Brownian motion.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+    octaves: u32,
+}
+
+impl NoiseTexture {
+    /// `scale` is the noise frequency: larger values give finer detail.
+    pub fn new(seed: u64, scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            octaves: 1,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let n = self.noise.fbm(&(self.scale * *p), self.octaves);
+        Color::new(1.0, 1.0, 1.0) * (0.5 * (1.0 + n)).clamp(0.0, 1.0)
+    }
+}
+
+/// Bands along the Z axis whose phase is disturbed by turbulence, giving
+/// veined stone.
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f64,
+    turbulence: f64,
+    octaves: u32,
+    base: Color,
+    vein: Color,
+}
+
+impl MarbleTexture {
+    /// `scale` sets how close together the veins are.
+    pub fn new(seed: u64, scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            turbulence: 10.0,
+            octaves: 7,
+            base: Color::new(1.0, 1.0, 1.0),
+            vein: Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// How strongly turbulence bends the veins.
+    pub fn with_turbulence(mut self, strength: f64) -> Self {
+        self.turbulence = strength;
+        self
+    }
+
+    /// Noise octaves summed into the turbulence.
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn with_colors(mut self, base: &Color, vein: &Color) -> Self {
+        self.base = *base;
+        self.vein = *vein;
+        self
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let phase = self.scale * p.z() + self.turbulence * self.noise.turbulence(p, self.octaves);
+        let t = 0.5 * (1.0 + phase.sin());
+        lerp(&self.vein, &self.base, t)
+    }
+}
+
+/// Concentric growth rings around the Y axis, made irregular by turbulence.
+pub struct WoodTexture {
+    noise: Perlin,
+    scale: f64,
+    turbulence: f64,
+    octaves: u32,
+    light: Color,
+    dark: Color,
+}
+
+impl WoodTexture {
+    /// `scale` is the number of rings per unit of radius.
+    pub fn new(seed: u64, scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            turbulence: 0.5,
+            octaves: 4,
+            light: Color::new(0.76, 0.54, 0.32),
+            dark: Color::new(0.36, 0.2, 0.09),
+        }
+    }
+
+    /// How far, in rings, turbulence displaces the grain.
+    pub fn with_turbulence(mut self, strength: f64) -> Self {
+        self.turbulence = strength;
+        self
+    }
+
+    /// Noise octaves summed into the turbulence.
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn with_colors(mut self, light: &Color, dark: &Color) -> Self {
+        self.light = *light;
+        self.dark = *dark;
+        self
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let radius = Vec3::new(p.x(), 0.0, p.z()).length();
+        let grain = self.turbulence * self.noise.turbulence(p, self.octaves);
+        let rings = self.scale * radius + grain;
+        // A sharpened sine keeps the dark late wood narrower than the light.
+        let t = (0.5 * (1.0 + (2.0 * PI * rings).sin())).powi(3);
+        lerp(&self.light, &self.dark, t)
+    }
+}
+
+fn lerp(a: &Color, b: &Color, t: f64) -> Color {
+    Color((1.0 - t) * a.0 + t * b.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color(Vec3 { e: [0.0; 3] });
+    const WHITE: Color = Color(Vec3 { e: [1.0; 3] });
+
+    #[test]
+    fn checker_alternates_between_cubes() {
+        let checker = CheckerTexture::from_colors(2.0, &BLACK, &WHITE);
+        for (p, expected) in [
+            ([0.5, 0.5, 0.5], BLACK),
+            ([2.5, 0.5, 0.5], WHITE),
+            ([2.5, 2.5, 0.5], BLACK),
+            ([2.5, 2.5, 2.5], WHITE),
+            // Negative coordinates keep alternating across zero.
+            ([-0.5, 0.5, 0.5], WHITE),
+            ([-0.5, -0.5, 0.5], BLACK),
+            ([-2.5, -0.5, 0.5], WHITE),
+        ] {
+            let [x, y, z] = p;
+            let found = checker.value(0.3, 0.7, &Point3::new(x, y, z));
+            assert_eq!(found.0.e, expected.0.e, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn checker_passes_uv_to_its_textures() {
+        // Odd cubes show an image: black on the left half, white on the right.
+        let mut image = Image::new(2, 1);
+        image.set_pixel(1, 0, WHITE);
+        let odd = Arc::new(ImageTexture::new(image).with_filter(Filter::Nearest));
+        let checker = CheckerTexture::new(1.0, Arc::new(SolidColor::new(&WHITE)), odd);
+
+        let odd_cube = Point3::new(1.5, 0.5, 0.5);
+        assert_eq!(checker.value(0.25, 0.5, &odd_cube).0.e, BLACK.0.e);
+        assert_eq!(checker.value(0.75, 0.5, &odd_cube).0.e, WHITE.0.e);
+        let even_cube = Point3::new(0.5, 0.5, 0.5);
+        assert_eq!(checker.value(0.25, 0.5, &even_cube).0.e, WHITE.0.e);
+    }
+}
@@ -0,0 +1,625 @@
+//! A small parser for the subset of TOML used by scene files: `[table]` and
+//! `[[array]]` headers, `key = value` pairs, strings, numbers, booleans,
+//! arrays (which may span lines) and inline tables. Every value remembers the
+//! line it came from so the scene loader can point at mistakes.
+
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl TomlError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TomlError {}
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub line: usize,
+    pub kind: ValueKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ValueKind {
+    Number(f64),
+    String(String),
+    // No scene key takes a boolean yet, but they still parse.
+    #[allow(dead_code)]
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            ValueKind::Number(_) => "number",
+            ValueKind::String(_) => "string",
+            ValueKind::Boolean(_) => "boolean",
+            ValueKind::Array(_) => "array",
+            ValueKind::Table(_) => "table",
+        }
+    }
+}
+
+/// Key/value pairs in source order.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    /// Line of the header or inline table that opened this table.
+    pub line: usize,
+    entries: Vec<(String, Value)>,
+    // Set once a `[header]` has claimed the table, so it cannot be opened twice.
+    defined: bool,
+}
+
+impl Table {
+    fn new(line: usize) -> Self {
+        Self {
+            line,
+            ..Self::default()
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    fn insert(&mut self, key: String, value: Value) -> Result<(), TomlError> {
+        if self.get(&key).is_some() {
+            return Err(TomlError::new(value.line, format!("duplicate key `{key}`")));
+        }
+        self.entries.push((key, value));
+        Ok(())
+    }
+}
+
+pub fn parse(source: &str) -> Result<Table, TomlError> {
+    Parser::new(source).parse_document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Table, TomlError> {
+        let mut root = Table::new(1);
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_whitespace_and_comments();
+            let Some(c) = self.peek() else {
+                return Ok(root);
+            };
+
+            if c == '[' {
+                let line = self.line;
+                self.bump();
+                let is_array = self.eat('[');
+                let path = self.parse_key_path()?;
+                self.expect(']')?;
+                if is_array {
+                    self.expect(']')?;
+                }
+                self.end_of_line()?;
+
+                let (last, parents) = path.split_last().expect("key path is never empty");
+                let parent = descend(&mut root, parents, line)?;
+                if is_array {
+                    let table = Value {
+                        line,
+                        kind: ValueKind::Table(Table::new(line)),
+                    };
+                    match parent.get_mut(last) {
+                        None => parent.insert(
+                            last.clone(),
+                            Value {
+                                line,
+                                kind: ValueKind::Array(vec![table]),
+                            },
+                        )?,
+                        Some(Value {
+                            kind: ValueKind::Array(items),
+                            ..
+                        }) => items.push(table),
+                        Some(_) => {
+                            return Err(TomlError::new(
+                                line,
+                                format!("`{last}` is already defined and is not an array"),
+                            ));
+                        }
+                    }
+                } else {
+                    match parent.get_mut(last) {
+                        None => {
+                            let mut table = Table::new(line);
+                            table.defined = true;
+                            parent.insert(
+                                last.clone(),
+                                Value {
+                                    line,
+                                    kind: ValueKind::Table(table),
+                                },
+                            )?;
+                        }
+                        Some(Value {
+                            kind: ValueKind::Table(table),
+                            ..
+                        }) if !table.defined => table.defined = true,
+                        Some(_) => {
+                            return Err(TomlError::new(
+                                line,
+                                format!("table `{}` is defined twice", path.join(".")),
+                            ));
+                        }
+                    }
+                }
+                current = path;
+            } else {
+                let line = self.line;
+                let key = self.parse_key()?;
+                self.skip_spaces();
+                self.expect('=')?;
+                let value = self.parse_value()?;
+                self.end_of_line()?;
+
+                descend(&mut root, &current, line)?.insert(key, value)?;
+            }
+        }
+    }
+
+    fn parse_key_path(&mut self) -> Result<Vec<String>, TomlError> {
+        let mut path = vec![self.parse_key()?];
+        self.skip_spaces();
+        while self.eat('.') {
+            path.push(self.parse_key()?);
+            self.skip_spaces();
+        }
+        Ok(path)
+    }
+
+    fn parse_key(&mut self) -> Result<String, TomlError> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') => self.parse_basic_string(),
+            Some('\'') => self.parse_literal_string(),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.bump();
+                }
+                if start == self.pos {
+                    return Err(self.unexpected("a key"));
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, TomlError> {
+        self.skip_spaces();
+        let line = self.line;
+        let kind =
+            match self.peek() {
+                Some('"') => ValueKind::String(self.parse_basic_string()?),
+                Some('\'') => ValueKind::String(self.parse_literal_string()?),
+                Some('[') => {
+                    self.bump();
+                    let mut items = Vec::new();
+                    loop {
+                        self.skip_whitespace_and_comments();
+                        if self.eat(']') {
+                            break;
+                        }
+                        items.push(self.parse_value()?);
+                        self.skip_whitespace_and_comments();
+                        if !self.eat(',') {
+                            self.skip_whitespace_and_comments();
+                            self.expect(']')?;
+                            break;
+                        }
+                    }
+                    ValueKind::Array(items)
+                }
+                Some('{') => {
+                    self.bump();
+                    let mut table = Table::new(line);
+                    table.defined = true;
+                    self.skip_spaces();
+                    if !self.eat('}') {
+                        loop {
+                            let key = self.parse_key()?;
+                            self.skip_spaces();
+                            self.expect('=')?;
+                            let value = self.parse_value()?;
+                            table.insert(key, value)?;
+                            self.skip_spaces();
+                            if !self.eat(',') {
+                                self.expect('}')?;
+                                break;
+                            }
+                        }
+                    }
+                    ValueKind::Table(table)
+                }
+                Some(c) if c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.' => {
+                    let start = self.pos;
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || "+-._".contains(c))
+                    {
+                        self.bump();
+                    }
+                    let word: String = self.chars[start..self.pos].iter().collect();
+                    match word.as_str() {
+                        "true" => ValueKind::Boolean(true),
+                        "false" => ValueKind::Boolean(false),
+                        _ => {
+                            let number = word.replace('_', "");
+                            ValueKind::Number(number.parse().map_err(|_| {
+                                TomlError::new(line, format!("invalid value `{word}`"))
+                            })?)
+                        }
+                    }
+                }
+                _ => return Err(self.unexpected("a value")),
+            };
+        Ok(Value { line, kind })
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, TomlError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some(c) => {
+                            return Err(TomlError::new(
+                                self.line,
+                                format!("unknown escape sequence `\\{c}`"),
+                            ));
+                        }
+                        None => break,
+                    };
+                    s.push(escaped);
+                }
+                Some('\n') | None => break,
+                Some(c) => s.push(c),
+            }
+        }
+        Err(TomlError::new(self.line, "unterminated string"))
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, TomlError> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(s),
+                Some('\n') | None => {
+                    return Err(TomlError::new(self.line, "unterminated string"));
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// After a header or key/value pair only a comment may follow on the line.
+    fn end_of_line(&mut self) -> Result<(), TomlError> {
+        self.skip_spaces();
+        if self.peek() == Some('#') {
+            self.skip_comment();
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some('\r') if self.chars.get(self.pos + 1) == Some(&'\n') => {
+                self.bump();
+                self.bump();
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected("end of line")),
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                self.skip_comment();
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.bump();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), TomlError> {
+        if self.eat(expected) {
+            return Ok(());
+        }
+        Err(self.unexpected(&format!("`{expected}`")))
+    }
+
+    fn unexpected(&self, expected: &str) -> TomlError {
+        let found = match self.peek() {
+            None => "end of file".to_string(),
+            Some('\n') => "end of line".to_string(),
+            Some(c) => format!("`{c}`"),
+        };
+        TomlError::new(self.line, format!("expected {expected}, found {found}"))
+    }
+}
+
+/// Walks `path` from `root`, creating implicit tables and stepping into the
+/// newest element of arrays of tables.
+fn descend<'t>(
+    root: &'t mut Table,
+    path: &[String],
+    line: usize,
+) -> Result<&'t mut Table, TomlError> {
+    let mut table = root;
+    for key in path {
+        if table.get(key).is_none() {
+            table.insert(
+                key.clone(),
+                Value {
+                    line,
+                    kind: ValueKind::Table(Table::new(line)),
+                },
+            )?;
+        }
+        table = match table.get_mut(key).map(|value| &mut value.kind) {
+            Some(ValueKind::Table(inner)) => inner,
+            Some(ValueKind::Array(items)) => match items.last_mut().map(|item| &mut item.kind) {
+                Some(ValueKind::Table(inner)) => inner,
+                _ => {
+                    return Err(TomlError::new(line, format!("`{key}` is not a table")));
+                }
+            },
+            _ => {
+                return Err(TomlError::new(line, format!("`{key}` is not a table")));
+            }
+        };
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: &Value) -> f64 {
+        match value.kind {
+            ValueKind::Number(n) => n,
+            _ => panic!("expected a number, found a {}", value.type_name()),
+        }
+    }
+
+    fn table(value: &Value) -> &Table {
+        match &value.kind {
+            ValueKind::Table(t) => t,
+            _ => panic!("expected a table, found a {}", value.type_name()),
+        }
+    }
+
+    fn array(value: &Value) -> &[Value] {
+        match &value.kind {
+            ValueKind::Array(items) => items,
+            _ => panic!("expected an array, found a {}", value.type_name()),
+        }
+    }
+
+    fn error(source: &str) -> TomlError {
+        parse(source).expect_err("source should not parse")
+    }
+
+    #[test]
+    fn values_and_their_lines() {
+        let root = parse(
+            "# header comment\n\
+             title = \"a \\\"quoted\\\" \\t name\" # trailing\n\
+             path = 'C:\\scenes'\n\
+             big = 1_000.5\n\
+             tiny = -2e-3\n\
+             on = true\n\
+             list = [\n  1,\n  2, # two\n  3,\n]\n\
+             inline = { a = [], b = { c = 4 } }\n",
+        )
+        .unwrap();
+
+        let title = root.get("title").unwrap();
+        assert_eq!(title.line, 2);
+        assert!(matches!(&title.kind, ValueKind::String(s) if s == "a \"quoted\" \t name"));
+        assert!(
+            matches!(&root.get("path").unwrap().kind, ValueKind::String(s) if s == "C:\\scenes")
+        );
+        assert_eq!(number(root.get("big").unwrap()), 1000.5);
+        assert_eq!(number(root.get("tiny").unwrap()), -0.002);
+        assert!(matches!(
+            root.get("on").unwrap().kind,
+            ValueKind::Boolean(true)
+        ));
+
+        let list = array(root.get("list").unwrap());
+        assert_eq!(list.iter().map(number).collect::<Vec<_>>(), [1.0, 2.0, 3.0]);
+        assert_eq!(list.iter().map(|v| v.line).collect::<Vec<_>>(), [8, 9, 10]);
+
+        let inline = table(root.get("inline").unwrap());
+        assert_eq!(inline.line, 12);
+        assert!(array(inline.get("a").unwrap()).is_empty());
+        assert_eq!(
+            number(table(inline.get("b").unwrap()).get("c").unwrap()),
+            4.0
+        );
+    }
+
+    #[test]
+    fn tables_and_arrays_of_tables() {
+        let root = parse(
+            "[camera]\nvfov = 20\n\n\
+             [materials.ground]\ntype = \"lambertian\"\n\n\
+             [[objects]]\nradius = 1\n\n\
+             [[objects]]\nradius = 2\n\
+             [objects.transform]\nscale = 3\n",
+        )
+        .unwrap();
+        assert_eq!(
+            root.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["camera", "materials", "objects"]
+        );
+
+        let camera = table(root.get("camera").unwrap());
+        assert_eq!(camera.line, 1);
+        assert_eq!(number(camera.get("vfov").unwrap()), 20.0);
+
+        let materials = table(root.get("materials").unwrap());
+        assert_eq!(table(materials.get("ground").unwrap()).line, 4);
+
+        let objects = array(root.get("objects").unwrap());
+        assert_eq!(objects.len(), 2);
+        assert_eq!(table(&objects[0]).line, 7);
+        let second = table(&objects[1]);
+        assert_eq!(number(second.get("radius").unwrap()), 2.0);
+        let transform = table(second.get("transform").unwrap());
+        assert_eq!(number(transform.get("scale").unwrap()), 3.0);
+    }
+
+    #[test]
+    fn windows_line_endings() {
+        let root = parse("[camera]\r\nvfov = 20\r\n").unwrap();
+        let camera = table(root.get("camera").unwrap());
+        assert_eq!(camera.get("vfov").unwrap().line, 2);
+    }
+
+    #[test]
+    fn syntax_errors_name_their_line() {
+        for (source, line, message) in [
+            ("a = 1\nb = \n", 2, "expected a value, found end of line"),
+            ("a = 1\n\nb = 2 3\n", 3, "expected end of line, found `3`"),
+            ("a = \"open\nb = 1\n", 2, "unterminated string"),
+            ("a = 'open\n", 2, "unterminated string"),
+            ("a = \"\\q\"\n", 1, "unknown escape sequence `\\q`"),
+            ("a = [1, 2\nb = 3\n", 2, "expected `]`, found `b`"),
+            ("a = { b = 1\n", 1, "expected `}`, found end of line"),
+            ("x = 1\na = nope\n", 2, "invalid value `nope`"),
+            ("[camera\nvfov = 1\n", 1, "expected `]`, found end of line"),
+            ("= 1\n", 1, "expected a key, found `=`"),
+            ("a 1\n", 1, "expected `=`, found `1`"),
+        ] {
+            let err = error(source);
+            assert_eq!(
+                (err.line, err.message.as_str()),
+                (line, message),
+                "{source:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn redefinitions_are_errors() {
+        for (source, line, message) in [
+            ("a = 1\na = 2\n", 2, "duplicate key `a`"),
+            ("[t]\n[t]\n", 2, "table `t` is defined twice"),
+            (
+                "t = 1\n[[t]]\n",
+                2,
+                "`t` is already defined and is not an array",
+            ),
+            ("t = 1\n[t.u]\n", 2, "`t` is not a table"),
+            ("x = { a = 1, a = 2 }\n", 1, "duplicate key `a`"),
+        ] {
+            let err = error(source);
+            assert_eq!(
+                (err.line, err.message.as_str()),
+                (line, message),
+                "{source:?}"
+            );
+        }
+
+        // A table made implicitly by a dotted header can still be defined once.
+        assert!(parse("[a.b]\n[a]\nc = 1\n").is_ok());
+    }
+}
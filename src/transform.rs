@@ -0,0 +1,637 @@
+use std::{ops::Mul, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    rtweekend::degrees_to_radians,
+    vec3::{Point3, Vec3, unit_vector},
+};
+
+/// A 4x4 affine transformation matrix, applied to column vectors. The bottom
+/// row is always `[0, 0, 0, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Self = Matrix4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn translation(offset: &Vec3) -> Self {
+        let mut matrix = Self::IDENTITY;
+        for (row, d) in matrix.m.iter_mut().zip(offset.e) {
+            row[3] = d;
+        }
+        matrix
+    }
+
+    /// Scales along each axis; negative factors mirror.
+    pub fn scaling(factors: &Vec3) -> Self {
+        let mut matrix = Self::IDENTITY;
+        for (i, s) in factors.e.into_iter().enumerate() {
+            matrix.m[i][i] = s;
+        }
+        matrix
+    }
+
+    /// Rotates counterclockwise about `axis` when looking back along it
+    /// toward the origin.
+    pub fn rotation(axis: &Vec3, degrees: f64) -> Self {
+        let [x, y, z] = unit_vector(axis).e;
+        let theta = degrees_to_radians(degrees);
+        let (sin, cos) = theta.sin_cos();
+        let t = 1.0 - cos;
+        Self {
+            m: [
+                [
+                    t * x * x + cos,
+                    t * x * y - sin * z,
+                    t * x * z + sin * y,
+                    0.0,
+                ],
+                [
+                    t * x * y + sin * z,
+                    t * y * y + cos,
+                    t * y * z - sin * x,
+                    0.0,
+                ],
+                [
+                    t * x * z - sin * y,
+                    t * y * z + sin * x,
+                    t * z * z + cos,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn rotation_x(degrees: f64) -> Self {
+        Self::rotation(&Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotation_y(degrees: f64) -> Self {
+        Self::rotation(&Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotation_z(degrees: f64) -> Self {
+        Self::rotation(&Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    /// The transformation that applies `self` first and `next` after it.
+    pub fn then(&self, next: &Matrix4) -> Self {
+        *next * *self
+    }
+
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    /// Transforms a direction, which unlike a point is not translated.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let row = |i: usize| self.m[i][0] * v.x() + self.m[i][1] * v.y() + self.m[i][2] * v.z();
+        Vec3::new(row(0), row(1), row(2))
+    }
+
+    /// Multiplies `v` by the transpose of the linear part. Called on the
+    /// inverse of a matrix, this carries surface normals through the matrix
+    /// so they stay perpendicular to the transformed surface.
+    pub fn transpose_transform_vector(&self, v: &Vec3) -> Vec3 {
+        let column = |j: usize| self.m[0][j] * v.x() + self.m[1][j] * v.y() + self.m[2][j] * v.z();
+        Vec3::new(column(0), column(1), column(2))
+    }
+
+    /// How much the matrix scales volumes, negative if it mirrors them.
+    pub fn determinant(&self) -> f64 {
+        let a = &self.m;
+        a[0][0] * (a[1][1] * a[2][2] - a[1][2] * a[2][1])
+            - a[0][1] * (a[1][0] * a[2][2] - a[1][2] * a[2][0])
+            + a[0][2] * (a[1][0] * a[2][1] - a[1][1] * a[2][0])
+    }
+
+    /// Returns `None` if the matrix collapses space, e.g. a zero scale, or
+    /// has entries that aren't finite.
+    pub fn inverse(&self) -> Option<Self> {
+        let a = &self.m;
+        // Cofactors of the linear 3x3 part.
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            a[r0][c0] * a[r1][c1] - a[r0][c1] * a[r1][c0]
+        };
+        let c = [
+            [
+                cofactor(1, 2, 1, 2),
+                -cofactor(1, 2, 0, 2),
+                cofactor(1, 2, 0, 1),
+            ],
+            [
+                -cofactor(0, 2, 1, 2),
+                cofactor(0, 2, 0, 2),
+                -cofactor(0, 2, 0, 1),
+            ],
+            [
+                cofactor(0, 1, 1, 2),
+                -cofactor(0, 1, 0, 2),
+                cofactor(0, 1, 0, 1),
+            ],
+        ];
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() || !a.iter().flatten().all(|x| x.is_finite()) {
+            return None;
+        }
+
+        // The inverse of the linear part is the transposed cofactors over the
+        // determinant; the translation is undone after it.
+        let mut inverse = Self::IDENTITY;
+        for (j, cofactors) in c.iter().enumerate() {
+            for (i, cofactor) in cofactors.iter().enumerate() {
+                inverse.m[i][j] = cofactor / det;
+            }
+        }
+        let t = inverse.transform_vector(&Vec3::new(a[0][3], a[1][3], a[2][3]));
+        for (row, d) in inverse.m.iter_mut().zip(t.e) {
+            row[3] = -d;
+        }
+        // Scales close to zero or huge can still overflow the inverse.
+        inverse
+            .m
+            .iter()
+            .flatten()
+            .all(|x| x.is_finite())
+            .then_some(inverse)
+    }
+
+    /// The smallest axis-aligned box holding every point of `bbox` once
+    /// transformed.
+    pub fn transform_box(&self, bbox: &Aabb) -> Aabb {
+        if (0..3).any(|axis| bbox.axis_interval(axis).size() < 0.0) {
+            return Aabb::EMPTY;
+        }
+
+        // Each output extent starts at the translation and grows by the
+        // smaller and larger product of every matrix entry with its input
+        // extent, which visits all eight corners without building them.
+        let mut extents = [Interval::new(0.0, 0.0); 3];
+        for (i, extent) in extents.iter_mut().enumerate() {
+            extent.min = self.m[i][3];
+            extent.max = self.m[i][3];
+            for j in 0..3 {
+                let m = self.m[i][j];
+                // Skipping zero entries keeps an unbounded axis from turning
+                // into NaN.
+                if m == 0.0 {
+                    continue;
+                }
+                let input = bbox.axis_interval(j);
+                let (a, b) = (m * input.min, m * input.max);
+                extent.min += a.min(b);
+                extent.max += a.max(b);
+            }
+        }
+        let [x, y, z] = extents;
+        Aabb::new(x, y, z)
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut product = Self { m: [[0.0; 4]; 4] };
+        for i in 0..4 {
+            for j in 0..4 {
+                product.m[i][j] = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        product
+    }
+}
+
+/// Places another object in the world through an affine matrix, so one mesh
+/// can be instanced many times or a box can be tilted.
+///
+/// Rays are carried into the object's own space, and the hit point and normal
+/// are carried back out. Nested transforms work, but composing their matrices
+/// with [`Matrix4::then`] into a single wrapper is cheaper.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    matrix: Matrix4,
+    inverse: Matrix4,
+    bbox: Aabb,
+}
+
+impl Transform {
+    /// Returns `None` if `matrix` can't be inverted, as with a zero scale
+    /// or a non-finite entry.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Matrix4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        let bbox = matrix.transform_box(&object.bounding_box());
+        Some(Self {
+            object,
+            matrix,
+            inverse,
+            bbox,
+        })
+    }
+
+    pub fn translate(object: Arc<dyn Hittable>, offset: &Vec3) -> Option<Self> {
+        Self::new(object, Matrix4::translation(offset))
+    }
+
+    /// Rotates about `axis` through the origin.
+    pub fn rotate(object: Arc<dyn Hittable>, axis: &Vec3, degrees: f64) -> Option<Self> {
+        Self::new(object, Matrix4::rotation(axis, degrees))
+    }
+
+    /// Scales about the origin.
+    pub fn scale(object: Arc<dyn Hittable>, factors: &Vec3) -> Option<Self> {
+        Self::new(object, Matrix4::scaling(factors))
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        hit_transformed(self.object.as_ref(), &self.matrix, &self.inverse, r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        self.object
+            .transmittance(&object_ray(&self.inverse, r), ray_t)
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        pdf_value_transformed(self.object.as_ref(), &self.inverse, r)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let to_object = self
+            .object
+            .random(&self.inverse.transform_point(origin), time);
+        self.matrix.transform_vector(&to_object)
+    }
+}
+
+/// A placement built the same way as a scene file's `transform`: a scale,
+/// then rotations about the X, Y and Z axes in turn, in degrees, then a
+/// translation.
+#[derive(Debug, Clone, Copy)]
+pub struct Pose {
+    pub scale: Vec3,
+    pub rotation: Vec3,
+    pub translation: Vec3,
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self {
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotation: Vec3::default(),
+            translation: Vec3::default(),
+        }
+    }
+}
+
+impl Pose {
+    pub fn matrix(&self) -> Matrix4 {
+        Matrix4::scaling(&self.scale)
+            .then(&Matrix4::rotation_x(self.rotation.x()))
+            .then(&Matrix4::rotation_y(self.rotation.y()))
+            .then(&Matrix4::rotation_z(self.rotation.z()))
+            .then(&Matrix4::translation(&self.translation))
+    }
+
+    /// Blends each component separately, so a rotation from 0 to 720 degrees
+    /// spins twice rather than not at all.
+    fn lerp(&self, other: &Pose, t: f64) -> Pose {
+        let mix = |a: Vec3, b: Vec3| (1.0 - t) * a + t * b;
+        Pose {
+            scale: mix(self.scale, other.scale),
+            rotation: mix(self.rotation, other.rotation),
+            translation: mix(self.translation, other.translation),
+        }
+    }
+}
+
+/// Moves another object from the `start` pose at time 0 to the `end` pose at
+/// time 1, holding still outside that range, for motion blur.
+pub struct AnimatedTransform {
+    object: Arc<dyn Hittable>,
+    start: Pose,
+    end: Pose,
+    bbox: Aabb,
+}
+
+impl AnimatedTransform {
+    pub fn new(object: Arc<dyn Hittable>, start: Pose, end: Pose) -> Self {
+        /// Poses sampled to bound the swept volume.
+        const STEPS: usize = 64;
+
+        let inner = object.bounding_box();
+        let corners: Vec<Point3> = (0..8)
+            .map(|k| {
+                Point3::new(
+                    if k & 1 == 0 { inner.x.min } else { inner.x.max },
+                    if k & 2 == 0 { inner.y.min } else { inner.y.max },
+                    if k & 4 == 0 { inner.z.min } else { inner.z.max },
+                )
+            })
+            .collect();
+
+        // Between two sampled poses a point strays from both by no more than
+        // the distance it travels, and the corners travel furthest.
+        let mut bbox = Aabb::EMPTY;
+        let mut step = 0.0f64;
+        let mut previous: Option<Matrix4> = None;
+        for i in 0..=STEPS {
+            let matrix = start.lerp(&end, i as f64 / STEPS as f64).matrix();
+            bbox = Aabb::from_boxes(&bbox, &matrix.transform_box(&inner));
+            if let Some(previous) = previous {
+                for corner in &corners {
+                    let moved = matrix.transform_point(corner) - previous.transform_point(corner);
+                    step = step.max(moved.length());
+                }
+            }
+            previous = Some(matrix);
+        }
+        if step.is_finite() && step > 0.0 {
+            bbox = Aabb::new(
+                bbox.x.expand(2.0 * step),
+                bbox.y.expand(2.0 * step),
+                bbox.z.expand(2.0 * step),
+            );
+        }
+
+        Self {
+            object,
+            start,
+            end,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let t = r.time().clamp(0.0, 1.0);
+        let matrix = self.start.lerp(&self.end, t).matrix();
+        // A pose passing through zero scale has no volume to hit.
+        let inverse = matrix.inverse()?;
+        hit_transformed(self.object.as_ref(), &matrix, &inverse, r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let t = r.time().clamp(0.0, 1.0);
+        match self.start.lerp(&self.end, t).matrix().inverse() {
+            Some(inverse) => self.object.transmittance(&object_ray(&inverse, r), ray_t),
+            None => 1.0,
+        }
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        let t = r.time().clamp(0.0, 1.0);
+        match self.start.lerp(&self.end, t).matrix().inverse() {
+            Some(inverse) => pdf_value_transformed(self.object.as_ref(), &inverse, r),
+            None => 0.0,
+        }
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let matrix = self.start.lerp(&self.end, time.clamp(0.0, 1.0)).matrix();
+        // Nothing to aim at while the pose is flattened; `pdf_value` is zero.
+        let Some(inverse) = matrix.inverse() else {
+            return Vec3::new(1.0, 0.0, 0.0);
+        };
+        let to_object = self.object.random(&inverse.transform_point(origin), time);
+        matrix.transform_vector(&to_object)
+    }
+}
+
+fn hit_transformed(
+    object: &dyn Hittable,
+    matrix: &Matrix4,
+    inverse: &Matrix4,
+    r: &Ray,
+    ray_t: Interval,
+) -> Option<HitRecord> {
+    let mut rec = object.hit(&object_ray(inverse, r), ray_t)?;
+
+    rec.p = matrix.transform_point(&rec.p);
+    // The inner hit already faced the normal against the ray, and the
+    // inverse transpose keeps that side, so `front_face` carries over.
+    rec.normal = unit_vector(&inverse.transpose_transform_vector(&rec.normal));
+    Some(rec)
+}
+
+/// The density with which `random` picks the direction of `r`, for an object
+/// sampled in the space `inverse` maps to.
+fn pdf_value_transformed(object: &dyn Hittable, inverse: &Matrix4, r: &Ray) -> f64 {
+    let direction = inverse.transform_vector(&unit_vector(r.direction()));
+    let length = direction.length();
+    if length == 0.0 {
+        return 0.0;
+    }
+    let inner = Ray::with_time(
+        &inverse.transform_point(r.origin()),
+        &(direction / length),
+        r.time(),
+    );
+    // A linear map B takes a small solid angle around the unit direction w
+    // to one |det B| / |B w|^3 times as large.
+    object.pdf_value(&inner) * inverse.determinant().abs() / (length * length * length)
+}
+
+/// `r` carried into the space `inverse` maps to. The direction is left
+/// unnormalized so `t` means the same in both spaces.
+fn object_ray(inverse: &Matrix4, r: &Ray) -> Ray {
+    Ray::with_time(
+        &inverse.transform_point(r.origin()),
+        &inverse.transform_vector(r.direction()),
+        r.time(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        quad::Quad,
+        rtweekend::seed_rng,
+        sphere::Sphere,
+        vec3::{dot, random_unit_vector},
+    };
+
+    const SAMPLES: usize = 40_000;
+
+    fn assert_identity(m: &Matrix4) {
+        for (i, row) in m.m.iter().enumerate() {
+            for (j, x) in row.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((x - expected).abs() < 1e-12, "{m:?}");
+            }
+        }
+    }
+
+    fn pose() -> Pose {
+        Pose {
+            scale: Vec3::new(1.0, 2.5, 0.5),
+            rotation: Vec3::new(30.0, -45.0, 10.0),
+            translation: Vec3::new(0.5, 3.0, -1.0),
+        }
+    }
+
+    /// Checks `light`, seen from `origin`, against an estimate of the solid
+    /// angle it covers: its pdf must integrate to one over all directions,
+    /// and the directions it samples must all find it.
+    fn assert_samples_solid_angle(light: &dyn Hittable, origin: &Point3) {
+        seed_rng(7);
+        let uniform: f64 = (0..SAMPLES)
+            .map(|_| {
+                let r = Ray::new(origin, &random_unit_vector());
+                4.0 * std::f64::consts::PI * light.pdf_value(&r)
+            })
+            .sum::<f64>()
+            / SAMPLES as f64;
+        assert!((uniform - 1.0).abs() < 0.1, "pdf integrates to {uniform}");
+
+        let mut solid_angle = 0.0;
+        for _ in 0..SAMPLES {
+            let r = Ray::new(origin, &light.random(origin, 0.0));
+            let pdf = light.pdf_value(&r);
+            assert!(pdf > 0.0, "sampled direction misses the light");
+            solid_angle += 1.0 / pdf;
+        }
+        solid_angle /= SAMPLES as f64;
+
+        let hits = (0..SAMPLES)
+            .filter(|_| {
+                let r = Ray::new(origin, &random_unit_vector());
+                light.hit(&r, Interval::new(0.001, f64::INFINITY)).is_some()
+            })
+            .count();
+        let expected = 4.0 * std::f64::consts::PI * hits as f64 / SAMPLES as f64;
+        assert!(
+            (solid_angle / expected - 1.0).abs() < 0.1,
+            "{solid_angle} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = pose().matrix();
+        let inverse = m.inverse().unwrap();
+        assert_identity(&(m * inverse));
+        assert_identity(&(inverse * m));
+        assert!((m.determinant() - 1.25).abs() < 1e-12);
+        assert!(
+            Matrix4::scaling(&Vec3::new(1.0, 0.0, 1.0))
+                .inverse()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn singular_transforms_are_refused() {
+        let sphere: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(&Point3::new(0.0, 0.0, 0.0), 1.0, None));
+        for factors in [
+            Vec3::new(1e-150, 1e-150, 1e-150),
+            Vec3::new(1e200, 1e200, 1e200),
+            Vec3::new(f64::NAN, 1.0, 1.0),
+            Vec3::new(f64::INFINITY, 1.0, 1.0),
+        ] {
+            assert!(
+                Transform::scale(sphere.clone(), &factors).is_none(),
+                "{factors:?}"
+            );
+        }
+        assert!(Transform::translate(sphere.clone(), &Vec3::new(0.0, f64::NAN, 0.0)).is_none());
+        assert!(Transform::rotate(sphere.clone(), &Vec3::new(0.0, 1.0, 0.0), f64::NAN).is_none());
+        assert!(Transform::scale(sphere, &Vec3::new(1e-100, 1.0, 1.0)).is_some());
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        let quad = Arc::new(Quad::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vec3::new(1.0, 0.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            None,
+        ));
+        let m = pose().matrix();
+        let object = Transform::new(quad, m).unwrap();
+        let target = m.transform_point(&Point3::new(0.3, 0.6, 0.0));
+        let origin = Point3::new(5.0, 5.0, 5.0);
+        let rec = object
+            .hit(
+                &Ray::new(&origin, &(target - origin)),
+                Interval::new(0.0, f64::INFINITY),
+            )
+            .unwrap();
+
+        assert!((rec.p - target).length() < 1e-9);
+        let edge = m.transform_vector(&Vec3::new(1.0, 0.0, 0.0));
+        assert!(dot(&rec.normal, &edge).abs() < 1e-9);
+        assert!((rec.normal.length() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn transformed_sphere_light_pdf() {
+        let sphere = Arc::new(Sphere::new(&Point3::new(0.0, 0.0, 0.0), 1.0, None));
+        let m = pose().matrix();
+        let light = Transform::new(sphere, m).unwrap();
+        assert_samples_solid_angle(&light, &m.transform_point(&Point3::new(0.6, 0.6, 1.0)));
+    }
+
+    #[test]
+    fn transformed_quad_light_pdf() {
+        let quad = Arc::new(Quad::new(
+            &Point3::new(-1.0, -1.0, 0.0),
+            &Vec3::new(2.0, 0.0, 0.0),
+            &Vec3::new(0.5, 2.0, 0.0),
+            None,
+        ));
+        let m = pose().matrix();
+        let light = Transform::new(quad, m).unwrap();
+        assert_samples_solid_angle(&light, &m.transform_point(&Point3::new(0.2, 0.0, 0.8)));
+    }
+
+    #[test]
+    fn animated_light_pdf() {
+        let quad = Arc::new(Quad::new(
+            &Point3::new(-1.0, -1.0, 0.0),
+            &Vec3::new(2.0, 0.0, 0.0),
+            &Vec3::new(0.0, 2.0, 0.0),
+            None,
+        ));
+        // At time 0, where the test rays are, the start pose holds.
+        let light = AnimatedTransform::new(quad, pose(), Pose::default());
+        let origin = pose().matrix().transform_point(&Point3::new(0.2, 0.0, 0.8));
+        assert_samples_solid_angle(&light, &origin);
+    }
+}
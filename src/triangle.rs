@@ -0,0 +1,430 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    bvh::BvhNode,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    rtweekend::random_f64,
+    vec3::{Point3, Vec3, dot, unit_vector},
+};
+
+/// A single triangle. Without explicit normals it is flat shaded, and without
+/// explicit UVs the barycentric coordinates of the hit are used as `u`/`v`.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    mat: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    pub fn new(a: &Point3, b: &Point3, c: &Point3, mat: Option<Arc<dyn Material>>) -> Self {
+        Self {
+            vertices: [*a, *b, *c],
+            normals: None,
+            uvs: None,
+            mat,
+            bbox: triangle_bbox(a, b, c),
+        }
+    }
+
+    /// Per-vertex normals, interpolated across the face for smooth shading.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let [a, b, c] = &self.vertices;
+        let (t, b1, b2) = intersect(a, b, c, r, ray_t)?;
+        Some(surface_hit(
+            r,
+            t,
+            [b1, b2],
+            &self.vertices,
+            self.normals.as_ref(),
+            self.uvs.as_ref(),
+            &self.mat,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        self.hit(r, Interval::new(0.001, f64::INFINITY))
+            .map_or(0.0, |rec| area_pdf(&self.vertices, r, rec.t))
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        random_point(&self.vertices) - *origin
+    }
+}
+
+/// One face of a `TriangleMesh`: indices into the mesh's vertex arrays.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshFace {
+    pub vertices: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+struct MeshData {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    faces: Vec<MeshFace>,
+    mat: Option<Arc<dyn Material>>,
+}
+
+/// A mesh face referring past the end of one of the mesh's arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshError {
+    pub face: usize,
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mesh face {} refers to a missing vertex", self.face)
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Triangles sharing vertex, normal and UV arrays, with a BVH over its faces.
+pub struct TriangleMesh {
+    bvh: BvhNode,
+    face_count: usize,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        faces: Vec<MeshFace>,
+        mat: Option<Arc<dyn Material>>,
+    ) -> Result<Self, MeshError> {
+        for (i, face) in faces.iter().enumerate() {
+            let in_range = |indices: Option<[usize; 3]>, len: usize| {
+                indices.is_none_or(|indices| indices.iter().all(|&k| k < len))
+            };
+            if !(in_range(Some(face.vertices), positions.len())
+                && in_range(face.normals, normals.len())
+                && in_range(face.uvs, uvs.len()))
+            {
+                return Err(MeshError { face: i });
+            }
+        }
+
+        let face_count = faces.len();
+        let data = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            faces,
+            mat,
+        });
+
+        let mut triangles: Vec<Arc<dyn Hittable>> = (0..face_count)
+            .map(|face| {
+                Arc::new(MeshTriangle {
+                    mesh: data.clone(),
+                    face,
+                }) as Arc<dyn Hittable>
+            })
+            .collect();
+
+        Ok(Self {
+            bvh: BvhNode::new(&mut triangles),
+            face_count,
+        })
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.face_count
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.bvh.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        self.bvh.pdf_value(r)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.bvh.random(origin, time)
+    }
+}
+
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [Point3; 3] {
+        let face = &self.mesh.faces[self.face];
+        face.vertices.map(|i| self.mesh.positions[i])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let vertices = self.vertices();
+        let [a, b, c] = &vertices;
+        let (t, b1, b2) = intersect(a, b, c, r, ray_t)?;
+
+        let face = &self.mesh.faces[self.face];
+        let normals = face.normals.map(|n| n.map(|i| self.mesh.normals[i]));
+        let uvs = face.uvs.map(|uv| uv.map(|i| self.mesh.uvs[i]));
+        Some(surface_hit(
+            r,
+            t,
+            [b1, b2],
+            &vertices,
+            normals.as_ref(),
+            uvs.as_ref(),
+            &self.mesh.mat,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.vertices();
+        triangle_bbox(&a, &b, &c)
+    }
+
+    fn pdf_value(&self, r: &Ray) -> f64 {
+        let vertices = self.vertices();
+        let [a, b, c] = &vertices;
+        intersect(a, b, c, r, Interval::new(0.001, f64::INFINITY))
+            .map_or(0.0, |(t, _, _)| area_pdf(&vertices, r, t))
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        random_point(&self.vertices()) - *origin
+    }
+}
+
+fn triangle_bbox(a: &Point3, b: &Point3, c: &Point3) -> Aabb {
+    Aabb::from_boxes(&Aabb::from_points(a, b), &Aabb::from_points(a, c))
+}
+
+/// Möller–Trumbore ray/triangle intersection. Returns the ray parameter and
+/// the barycentric weights of `b` and `c`.
+fn intersect(
+    a: &Point3,
+    b: &Point3,
+    c: &Point3,
+    r: &Ray,
+    ray_t: Interval,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = *b - *a;
+    let edge2 = *c - *a;
+
+    let pvec = r.direction().cross(&edge2);
+    let det = dot(&edge1, &pvec);
+    // The ray lies in the triangle's plane, or the triangle is degenerate.
+    // `det` grows with the edges and the direction, so the cutoff does too,
+    // leaving tiny triangles hittable.
+    let scale = edge1.length_squared() * edge2.length_squared() * r.direction().length_squared();
+    if det * det < 1e-24 * scale {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = *r.origin() - *a;
+    let b1 = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = tvec.cross(&edge1);
+    let b2 = dot(r.direction(), &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = dot(&edge2, &qvec) * inv_det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+/// The density over solid angle of `r` from picking points uniformly on the
+/// triangle, where `r` meets it at `t`.
+fn area_pdf([a, b, c]: &[Point3; 3], r: &Ray, t: f64) -> f64 {
+    let cross = (*b - *a).cross(&(*c - *a));
+    let area = 0.5 * cross.length();
+    let distance_squared = t * t * r.direction().length_squared();
+    let cosine = dot(r.direction(), &cross).abs() / (r.direction().length() * cross.length());
+    distance_squared / (cosine * area)
+}
+
+/// A point picked uniformly on the triangle, folding the unit square's far
+/// half back onto it.
+fn random_point([a, b, c]: &[Point3; 3]) -> Point3 {
+    let (mut s, mut t) = (random_f64(), random_f64());
+    if s + t > 1.0 {
+        (s, t) = (1.0 - s, 1.0 - t);
+    }
+    *a + s * (*b - *a) + t * (*c - *a)
+}
+
+fn surface_hit(
+    r: &Ray,
+    t: f64,
+    [b1, b2]: [f64; 2],
+    vertices: &[Point3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: Option<&[(f64, f64); 3]>,
+    mat: &Option<Arc<dyn Material>>,
+) -> HitRecord {
+    let b0 = 1.0 - b1 - b2;
+    let [a, b, c] = vertices;
+    let geometric_normal = unit_vector(&(*b - *a).cross(&(*c - *a)));
+
+    let (u, v) = match uvs {
+        Some([uv0, uv1, uv2]) => (
+            b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+            b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+        ),
+        None => (b1, b2),
+    };
+
+    let mut rec = HitRecord {
+        t,
+        p: r.at(t),
+        u,
+        v,
+        mat: mat.clone(),
+        ..HitRecord::default()
+    };
+
+    // Sidedness comes from the true surface; the interpolated normal only
+    // changes shading, and is kept on the same side as the geometry.
+    rec.set_face_normal(r, &geometric_normal);
+    if let Some([n0, n1, n2]) = normals {
+        let mut shading_normal = unit_vector(&(b0 * *n0 + b1 * *n1 + b2 * *n2));
+        if dot(&shading_normal, &geometric_normal) < 0.0 {
+            shading_normal = -shading_normal;
+        }
+        rec.normal = if rec.front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+    }
+    rec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quad::Quad;
+
+    fn face(vertices: [usize; 3]) -> MeshFace {
+        MeshFace {
+            vertices,
+            normals: None,
+            uvs: None,
+        }
+    }
+
+    /// The unit square at z = 0 as two triangles.
+    fn square() -> TriangleMesh {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let faces = vec![face([0, 1, 2]), face([0, 2, 3])];
+        TriangleMesh::new(positions, vec![], vec![], faces, None).unwrap()
+    }
+
+    #[test]
+    fn faces_past_the_arrays_are_errors() {
+        let positions = vec![Point3::new(0.0, 0.0, 0.0); 3];
+        let faces = vec![face([0, 1, 2]), face([0, 1, 3])];
+        let err = TriangleMesh::new(positions.clone(), vec![], vec![], faces, None).err();
+        assert_eq!(err, Some(MeshError { face: 1 }));
+
+        let with_normals = MeshFace {
+            normals: Some([0, 0, 1]),
+            ..face([0, 1, 2])
+        };
+        let normals = vec![Vec3::new(0.0, 0.0, 1.0)];
+        let err = TriangleMesh::new(positions, normals, vec![], vec![with_normals], None).err();
+        assert_eq!(err, Some(MeshError { face: 0 }));
+    }
+
+    #[test]
+    fn tiny_triangles_are_hit() {
+        for size in [1.0, 1e-4, 1e-8] {
+            let triangle = Triangle::new(
+                &Point3::new(0.0, 0.0, 0.0),
+                &Point3::new(size, 0.0, 0.0),
+                &Point3::new(0.0, size, 0.0),
+                None,
+            );
+            let target = Point3::new(0.25 * size, 0.25 * size, 0.0);
+            let origin = Point3::new(0.0, 0.0, 1.0);
+            let rec = triangle
+                .hit(
+                    &Ray::new(&origin, &(target - origin)),
+                    Interval::new(0.001, f64::INFINITY),
+                )
+                .unwrap_or_else(|| panic!("missed the triangle of size {size}"));
+            assert!((rec.u - 0.25).abs() < 1e-6 && (rec.v - 0.25).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn mesh_light_pdf_matches_the_quad() {
+        let mesh = square();
+        let quad = Quad::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vec3::new(1.0, 0.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            None,
+        );
+        let origin = Point3::new(0.3, -0.4, 2.0);
+        for target in [(0.1, 0.2), (0.8, 0.3), (0.5, 0.9), (1.5, 0.5)] {
+            let r = Ray::new(&origin, &(Point3::new(target.0, target.1, 0.0) - origin));
+            let (expected, found) = (quad.pdf_value(&r), mesh.pdf_value(&r));
+            assert!(
+                (expected - found).abs() <= 1e-12 * expected,
+                "{expected} vs {found}"
+            );
+        }
+
+        for _ in 0..100 {
+            let direction = mesh.random(&origin, 0.0);
+            let p = origin + direction;
+            assert!(p.z().abs() < 1e-12);
+            assert!((0.0..=1.0).contains(&p.x()) && (0.0..=1.0).contains(&p.y()));
+            assert!(mesh.pdf_value(&Ray::new(&origin, &direction)) > 0.0);
+        }
+    }
+}
@@ -75,7 +75,7 @@ pub fn dot(vec1: &Vec3, vec2: &Vec3) -> f64 {
 #[inline]
 pub fn unit_vector(vec: &Vec3) -> Vec3 {
     let len = vec.length();
-    vec.clone() / len
+    *vec / len
 }
 
 #[inline]
@@ -112,6 +112,19 @@ pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
     -on_unit_sphere
 }
 
+/// A direction about +Z, chosen with density `cos(theta) / pi`.
+#[inline]
+pub fn random_cosine_direction() -> Vec3 {
+    let r1 = random_f64();
+    let r2 = random_f64();
+
+    let phi = 2.0 * std::f64::consts::PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+    Vec3::new(x, y, z)
+}
+
 #[inline]
 pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
     *v - (2.0 * dot(v, n) * (*n))
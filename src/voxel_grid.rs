@@ -0,0 +1,308 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    ops::{Add, Mul},
+    path::Path,
+};
+
+use crate::{color::Color, perlin::Perlin, vec3::Point3};
+
+/// Values on a regular lattice of voxels spanning the unit cube, looked up
+/// with trilinear interpolation between voxel centers.
+///
+/// Voxels are stored with X varying fastest, then Y, then Z.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid<T> {
+    dims: [usize; 3],
+    values: Vec<T>,
+}
+
+impl<T: Copy> VoxelGrid<T> {
+    /// # Panics
+    ///
+    /// If a dimension is zero or `values` does not hold one value per voxel.
+    pub fn new(dims: [usize; 3], values: Vec<T>) -> Self {
+        assert!(
+            dims.iter().all(|&n| n > 0),
+            "voxel grid dimensions must be non-zero"
+        );
+        assert_eq!(
+            values.len(),
+            dims[0] * dims[1] * dims[2],
+            "voxel grid needs one value per voxel"
+        );
+        Self { dims, values }
+    }
+
+    /// A single voxel, the same everywhere.
+    pub fn uniform(value: T) -> Self {
+        Self::new([1, 1, 1], vec![value])
+    }
+
+    /// Evaluates `f` at the center of every voxel, in unit-cube coordinates.
+    pub fn from_fn(dims: [usize; 3], mut f: impl FnMut(&Point3) -> T) -> Self {
+        let [nx, ny, nz] = dims;
+        let mut values = Vec::with_capacity(nx * ny * nz);
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    values.push(f(&Point3::new(
+                        (i as f64 + 0.5) / nx as f64,
+                        (j as f64 + 0.5) / ny as f64,
+                        (k as f64 + 0.5) / nz as f64,
+                    )));
+                }
+            }
+        }
+        Self::new(dims, values)
+    }
+
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> T {
+        self.values[(k * self.dims[1] + j) * self.dims[0] + i]
+    }
+}
+
+impl<T> VoxelGrid<T>
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    /// The value at `p` in unit-cube coordinates. Points outside the cube
+    /// take the value of the nearest face.
+    pub fn sample(&self, p: &Point3) -> T {
+        // Per axis: the two neighbouring voxel indices and the blend weight.
+        let axis = |a: usize| {
+            let n = self.dims[a];
+            let x = (p.e[a] * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i0 = x.floor() as usize;
+            (i0, (i0 + 1).min(n - 1), x - i0 as f64)
+        };
+        let (i0, i1, fx) = axis(0);
+        let (j0, j1, fy) = axis(1);
+        let (k0, k1, fz) = axis(2);
+
+        let lerp = |a: T, b: T, t: f64| a * (1.0 - t) + b * t;
+        let row = |j: usize, k: usize| lerp(self.voxel(i0, j, k), self.voxel(i1, j, k), fx);
+        let slice = |k: usize| lerp(row(j0, k), row(j1, k), fy);
+        lerp(slice(k0), slice(k1), fz)
+    }
+}
+
+impl VoxelGrid<f64> {
+    /// Loads a text grid: the three dimensions, then one number per voxel,
+    /// separated by whitespace. `#` starts a comment.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (dims, numbers) = read_ascii(&mut BufReader::new(File::open(path)?), 1)?;
+        Ok(Self::new(dims, numbers))
+    }
+
+    /// Loads a headerless file of little-endian 32-bit floats, one per voxel.
+    pub fn load_raw(path: impl AsRef<Path>, dims: [usize; 3]) -> io::Result<Self> {
+        let numbers = read_raw(&mut BufReader::new(File::open(path)?), dims, 1)?;
+        Ok(Self::new(dims, numbers))
+    }
+
+    /// A cloud-like blob: fractal Perlin noise at `scale` times unit-cube
+    /// coordinates, densest toward the center and fading to nothing at the
+    /// sphere inscribed in the cube so the grid's box does not show.
+    pub fn noise_cloud(dims: [usize; 3], seed: u64, scale: f64, octaves: u32) -> Self {
+        let perlin = Perlin::new(seed);
+        let center = Point3::new(0.5, 0.5, 0.5);
+        Self::from_fn(dims, |p| {
+            let falloff = (1.0 - 2.0 * (*p - center).length()).max(0.0);
+            let noise = 0.3 + 1.5 * perlin.fbm(&(scale * *p), octaves.max(1));
+            (falloff * noise).max(0.0)
+        })
+    }
+
+    pub fn max(&self) -> f64 {
+        self.values.iter().copied().fold(0.0, f64::max)
+    }
+}
+
+impl VoxelGrid<Color> {
+    /// Like [`VoxelGrid::<f64>::load`], with three numbers (red, green and
+    /// blue, linear) per voxel.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (dims, numbers) = read_ascii(&mut BufReader::new(File::open(path)?), 3)?;
+        Ok(Self::new(dims, to_colors(&numbers)))
+    }
+
+    /// Like [`VoxelGrid::<f64>::load_raw`], with three floats per voxel.
+    pub fn load_raw(path: impl AsRef<Path>, dims: [usize; 3]) -> io::Result<Self> {
+        let numbers = read_raw(&mut BufReader::new(File::open(path)?), dims, 3)?;
+        Ok(Self::new(dims, to_colors(&numbers)))
+    }
+}
+
+fn to_colors(numbers: &[f64]) -> Vec<Color> {
+    numbers
+        .chunks_exact(3)
+        .map(|c| Color::new(c[0], c[1], c[2]))
+        .collect()
+}
+
+/// Reads the dimensions and `channels` numbers per voxel of a text grid.
+fn read_ascii(input: &mut impl Read, channels: usize) -> io::Result<([usize; 3], Vec<f64>)> {
+    let mut text = String::new();
+    input
+        .read_to_string(&mut text)
+        .map_err(|_| invalid("file is not text"))?;
+    let mut tokens = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(str::split_whitespace);
+
+    let mut dims = [0; 3];
+    for n in &mut dims {
+        *n = match tokens.next().map(str::parse) {
+            Some(Ok(n)) if n > 0 => n,
+            _ => return Err(invalid("expected three non-zero dimensions")),
+        };
+    }
+
+    let count = value_count(dims, channels)?;
+    let numbers = tokens
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .ok()
+                .filter(|x| x.is_finite())
+                .ok_or_else(|| invalid(&format!("`{token}` is not a number")))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    if numbers.len() != count {
+        return Err(invalid(&format!(
+            "expected {count} values for a {}x{}x{} grid, found {}",
+            dims[0],
+            dims[1],
+            dims[2],
+            numbers.len()
+        )));
+    }
+    Ok((dims, numbers))
+}
+
+fn read_raw(input: &mut impl Read, dims: [usize; 3], channels: usize) -> io::Result<Vec<f64>> {
+    let bytes = value_count(dims, channels)?
+        .checked_mul(4)
+        .ok_or_else(|| invalid("invalid grid dimensions"))?;
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    if data.len() != bytes {
+        return Err(invalid(&format!(
+            "expected {bytes} bytes for a {}x{}x{} grid, found {}",
+            dims[0],
+            dims[1],
+            dims[2],
+            data.len()
+        )));
+    }
+    data.chunks_exact(4)
+        .map(|b| {
+            let x = f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64;
+            if x.is_finite() {
+                Ok(x)
+            } else {
+                Err(invalid("grid holds a value that is not finite"))
+            }
+        })
+        .collect()
+}
+
+/// The number of values in a grid of `dims` voxels with `channels` each.
+fn value_count(dims: [usize; 3], channels: usize) -> io::Result<usize> {
+    dims.iter()
+        .try_fold(channels, |count, &n| count.checked_mul(n))
+        .filter(|&count| count > 0)
+        .ok_or_else(|| invalid("invalid grid dimensions"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("grid: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_floats(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn samples_interpolate_between_voxel_centers() {
+        let grid = VoxelGrid::new([2, 1, 1], vec![0.0, 1.0]);
+        assert_eq!(grid.sample(&Point3::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.sample(&Point3::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.sample(&Point3::new(0.75, 0.5, 0.5)), 1.0);
+        // Outside the cube, the nearest face.
+        assert_eq!(grid.sample(&Point3::new(2.0, -1.0, 0.5)), 1.0);
+    }
+
+    #[test]
+    fn reads_text_grids_with_comments() {
+        let text = "# dims\n2 1 2\n0 1 # first row\n2 3\n";
+        let (dims, numbers) = read_ascii(&mut text.as_bytes(), 1).unwrap();
+        assert_eq!(dims, [2, 1, 2]);
+        assert_eq!(numbers, [0.0, 1.0, 2.0, 3.0]);
+
+        let grid = VoxelGrid::new(dims, numbers);
+        assert_eq!(grid.sample(&Point3::new(0.75, 0.5, 0.75)), 3.0);
+    }
+
+    #[test]
+    fn text_grid_errors() {
+        for (text, message) in [
+            ("2 2 2\n1 2 3", "expected 8 values"),
+            ("1 1 1\n1 2", "expected 1 values"),
+            ("1 0 1\n", "three non-zero dimensions"),
+            ("1 1 1\nnan", "`nan` is not a number"),
+            (
+                "99999999999 99999999999 99999999999\n",
+                "invalid grid dimensions",
+            ),
+        ] {
+            let err = read_ascii(&mut text.as_bytes(), 1).unwrap_err();
+            assert!(err.to_string().contains(message), "{err}");
+        }
+        let err = read_ascii(&mut "1 1 1\n1 2".as_bytes(), 3).unwrap_err();
+        assert!(err.to_string().contains("expected 3 values"), "{err}");
+    }
+
+    #[test]
+    fn reads_raw_floats() {
+        let data = raw_floats(&[0.5, 1.0, 1.5, 2.0, 2.5, 3.0]);
+        let numbers = read_raw(&mut data.as_slice(), [2, 1, 1], 3).unwrap();
+        assert_eq!(to_colors(&numbers)[1].0.e, [2.0, 2.5, 3.0]);
+    }
+
+    #[test]
+    fn raw_size_mismatch_is_an_error() {
+        let data = raw_floats(&[1.0, 2.0, 3.0]);
+        let err = read_raw(&mut data.as_slice(), [2, 2, 1], 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string()
+                .contains("expected 16 bytes for a 2x2x1 grid, found 12"),
+            "{err}"
+        );
+
+        let data = raw_floats(&[f32::INFINITY]);
+        assert!(read_raw(&mut data.as_slice(), [1, 1, 1], 1).is_err());
+    }
+
+    #[test]
+    fn overflowing_raw_dimensions_are_an_error() {
+        let dims = [1 << 30, 1 << 30, 1 << 2];
+        assert!(read_raw(&mut [].as_slice(), dims, 3).is_err());
+        assert!(read_raw(&mut [].as_slice(), [usize::MAX, 1, 1], 1).is_err());
+    }
+}
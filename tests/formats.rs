@@ -0,0 +1,126 @@
+//! The public file readers and writers, through files on disk.
+
+use std::{fs, path::PathBuf};
+
+use ray_tracing::{Color, Hittable, Image, ImageFormat, obj, ply};
+
+/// A path in the temp directory unique to this test process.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ray-tracing-{}-{name}", std::process::id()))
+}
+
+fn gradient_image() -> Image {
+    let mut image = Image::new(9, 4);
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let c = Color::new(x as f64 / 8.0, y as f64 / 3.0, 0.5);
+            image.set_pixel(x, y, c);
+        }
+    }
+    image
+}
+
+#[test]
+fn images_survive_a_save_and_load() {
+    let image = gradient_image();
+    let mut loaded = Vec::new();
+    for name in ["image.png", "image.ppm", "image.hdr"] {
+        let path = temp_path(name);
+        image.save(&path).unwrap();
+        loaded.push(Image::load(&path).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+    let [png, ppm, hdr] = &loaded[..] else {
+        unreachable!()
+    };
+
+    // PNG and PPM both keep the same 8-bit samples.
+    assert_eq!((png.width(), png.height()), (9, 4));
+    assert!(
+        png.pixels()
+            .iter()
+            .zip(ppm.pixels())
+            .all(|(a, b)| a.0.e == b.0.e)
+    );
+
+    // HDR keeps linear values to within its 8-bit shared-exponent mantissa.
+    for (written, read) in image.pixels().iter().zip(hdr.pixels()) {
+        for (a, b) in written.0.e.iter().zip(read.0.e) {
+            assert!((a - b).abs() <= 1.0 / 128.0, "{a} vs {b}");
+        }
+    }
+}
+
+#[test]
+fn image_formats_come_from_names_and_extensions() {
+    assert_eq!(ImageFormat::from_name("P3"), Some(ImageFormat::Ppm));
+    assert_eq!(
+        ImageFormat::from_path("out.HDR".as_ref()),
+        Some(ImageFormat::Hdr)
+    );
+    assert!(Image::load("picture.bmp").is_err());
+
+    let path = temp_path("image.pfm");
+    gradient_image().save(&path).unwrap();
+    assert!(Image::load(&path).is_err());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn corrupt_images_are_errors_not_panics() {
+    for (name, bytes) in [
+        ("huge.ppm", &b"P6 4294967295 4294967295 255\n\0"[..]),
+        (
+            "huge.hdr",
+            b"#?RADIANCE\n\n-Y 2000000000 +X 2000000000\n\0\0\0\0",
+        ),
+        ("empty.png", b""),
+    ] {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        assert!(Image::load(&path).is_err(), "{name}");
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn obj_meshes_load_and_report_error_lines() {
+    let dir = temp_path("obj");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("quad.mtl"), "newmtl red\nKd 0.8 0.1 0.1\n").unwrap();
+    fs::write(
+        dir.join("quad.obj"),
+        "mtllib quad.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nusemtl red\nf 1 2 3 4\n",
+    )
+    .unwrap();
+
+    let mesh = obj::load_obj(dir.join("quad.obj")).unwrap();
+    let bbox = mesh.bounding_box();
+    assert!(bbox.x.min.abs() < 1e-3 && (bbox.x.max - 1.0).abs() < 1e-3);
+    assert!(bbox.y.min.abs() < 1e-3 && (bbox.y.max - 1.0).abs() < 1e-3);
+
+    fs::write(dir.join("bad.obj"), "v 0 0 0\nv 1 0 0\n\nf 1 2 9\n").unwrap();
+    let err = obj::load_obj(dir.join("bad.obj")).err().unwrap();
+    let message = err.to_string();
+    assert!(message.contains("bad.obj:4:"), "{message}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ply_meshes_load() {
+    let path = temp_path("tri.ply");
+    fs::write(
+        &path,
+        "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+         property float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+         0 0 0\n2 0 0\n0 2 0\n3 0 1 2\n",
+    )
+    .unwrap();
+    let mesh = ply::load_ply(&path).unwrap();
+    assert!((mesh.bounding_box().x.max - 2.0).abs() < 1e-3);
+    fs::remove_file(&path).unwrap();
+
+    let err = ply::load_ply(&path).err().unwrap();
+    assert!(matches!(err, ply::PlyError::Io { .. }));
+}
@@ -0,0 +1,151 @@
+//! Small seeded renders, checked against values known in closed form.
+
+use std::sync::Arc;
+
+use ray_tracing::{
+    Background, Camera, CameraBuilder, Color, DiffuseLight, HittableList, Image, Lambertian,
+    Point3, Quad, Sphere, Vec3,
+};
+
+fn camera() -> CameraBuilder {
+    Camera::builder()
+        .image_width(16)
+        .samples_per_pixel(16)
+        .max_depth(8)
+        .vfov(40.0)
+        .lookfrom(&Point3::new(0.0, 0.0, 4.0))
+        .lookat(&Point3::new(0.0, 0.0, 0.0))
+        .seed(11)
+}
+
+fn mean(image: &Image) -> f64 {
+    let sum: f64 = image
+        .pixels()
+        .iter()
+        .map(|c| c.0.e.iter().sum::<f64>())
+        .sum();
+    sum / (3 * image.pixels().len()) as f64
+}
+
+#[test]
+fn camera_settings_are_kept() {
+    let camera = camera().aspect_ratio(2.0).build();
+    assert_eq!((camera.image_width(), camera.image_height()), (16, 8));
+    assert_eq!(camera.samples_per_pixel(), 16);
+    assert_eq!(camera.lookfrom().e, [0.0, 0.0, 4.0]);
+    assert_eq!(camera.vfov(), 40.0);
+}
+
+#[test]
+fn seeded_renders_repeat_on_any_number_of_threads() {
+    let mut world = HittableList::default();
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(&Color::new(0.2, 0.6, 0.3)))),
+    )));
+    let lights: HittableList = HittableList::default();
+
+    let one = camera().threads(1).build().render(&world, &lights);
+    let many = camera().threads(4).build().render(&world, &lights);
+    assert!(
+        one.pixels()
+            .iter()
+            .zip(many.pixels())
+            .all(|(a, b)| a.0.e == b.0.e)
+    );
+}
+
+#[test]
+fn white_furnace() {
+    // A convex diffuse object under a uniform sky reflects its albedo of the
+    // sky at every point, whatever the sampling.
+    let mut world = HittableList::default();
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)))),
+    )));
+    let camera = camera()
+        .samples_per_pixel(64)
+        .background(Background::Solid(Color::new(1.0, 1.0, 1.0)))
+        .build();
+    let no_lights: HittableList = HittableList::default();
+    let image = camera.render(&world, &no_lights);
+
+    let center = image.pixel(8, 8);
+    assert!((center.0.x() - 0.5).abs() < 0.1, "{}", center.0);
+    let corner = image.pixel(0, 0);
+    assert_eq!(corner.0.e, [1.0; 3]);
+}
+
+#[test]
+fn lights_are_seen_directly_and_by_what_they_light() {
+    // Inside a glowing shell, every camera ray ends on it: pixels showing
+    // the shell are its emission, and a convex diffuse ball in the middle
+    // reflects its albedo of that.
+    let glow = Color::new(0.25, 0.5, 1.0);
+    let shell = Arc::new(Sphere::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        10.0,
+        Some(Arc::new(DiffuseLight::new(&glow))),
+    ));
+    let mut world = HittableList::default();
+    world.add(shell.clone());
+    world.add(Arc::new(Sphere::new(
+        &Point3::new(0.0, 0.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)))),
+    )));
+    let camera = camera()
+        .background(Background::Solid(Color::default()))
+        .build();
+
+    let no_lights: HittableList = HittableList::default();
+    let image = camera.render(&world, &no_lights);
+    assert_eq!(image.pixel(0, 0).0.e, glow.0.e);
+    for (found, expected) in image.pixel(8, 8).0.e.iter().zip([0.125, 0.25, 0.5]) {
+        assert!((found - expected).abs() < 1e-12, "{}", image.pixel(8, 8).0);
+    }
+
+    // Sampling the shell as a light changes only the noise.
+    let mut lights = HittableList::default();
+    lights.add(shell);
+    let image = camera.render(&world, &lights);
+    assert_eq!(image.pixel(0, 0).0.e, glow.0.e);
+    let center = image.pixel(8, 8);
+    assert!((center.0.z() - 0.5).abs() < 0.05, "{}", center.0);
+}
+
+#[test]
+fn sampling_lights_does_not_change_the_expected_image() {
+    let lamp = Arc::new(Quad::new(
+        &Point3::new(-0.5, 1.5, -0.5),
+        &Vec3::new(1.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 1.0),
+        Some(Arc::new(DiffuseLight::new(&Color::new(8.0, 8.0, 8.0)))),
+    ));
+    let mut world = HittableList::default();
+    world.add(lamp.clone());
+    world.add(Arc::new(Quad::new(
+        &Point3::new(-3.0, -1.0, -3.0),
+        &Vec3::new(6.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 6.0),
+        Some(Arc::new(Lambertian::new(&Color::new(0.6, 0.6, 0.6)))),
+    )));
+    let mut lights = HittableList::default();
+    lights.add(lamp);
+
+    let camera = camera()
+        .lookfrom(&Point3::new(0.0, 2.0, 5.0))
+        .samples_per_pixel(128)
+        .background(Background::Solid(Color::default()))
+        .build();
+    let sampled = mean(&camera.render(&world, &lights));
+    let no_lights: HittableList = HittableList::default();
+    let unsampled = mean(&camera.render(&world, &no_lights));
+    assert!(
+        (sampled / unsampled - 1.0).abs() < 0.1,
+        "{sampled} vs {unsampled}"
+    );
+}
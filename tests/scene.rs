@@ -0,0 +1,137 @@
+//! Loading scene files and the built-in scenes.
+
+use std::{fs, path::Path};
+
+use ray_tracing::{Hittable, Scene, SceneError, scenes};
+
+fn error_line(source: &str) -> (usize, String) {
+    match Scene::parse(source) {
+        Err(SceneError::Invalid { line, message }) => (line, message),
+        Err(SceneError::Io(err)) => panic!("unexpected I/O error: {err}"),
+        Ok(_) => panic!("scene loaded:\n{source}"),
+    }
+}
+
+#[test]
+fn bundled_scene_loads() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/three_spheres.toml");
+    let scene = Scene::load(path).unwrap();
+    assert_eq!(scene.camera.image_width, 400);
+    assert_eq!(scene.camera.defocus_angle, 0.6);
+    assert_eq!(scene.world.objects().len(), 4);
+    assert!(scene.lights.objects().is_empty());
+
+    let camera = scene.camera.build();
+    assert_eq!(camera.image_height(), 224);
+    assert_eq!(camera.focus_dist(), 10.0);
+}
+
+#[test]
+fn every_preset_builds() {
+    for name in scenes::PRESETS {
+        let scene = scenes::preset(name).unwrap();
+        assert!(!scene.world.objects().is_empty(), "{name}");
+    }
+    assert!(scenes::preset("nowhere").is_none());
+}
+
+#[test]
+fn emissive_shapes_are_lights_even_when_moved() {
+    let scene = Scene::parse(
+        r#"
+        [materials.lamp]
+        type = "diffuse_light"
+        emit = [4, 4, 4]
+
+        [[objects]]
+        type = "quad"
+        q = [0, 0, 0]
+        u = [1, 0, 0]
+        v = [0, 1, 0]
+        material = "lamp"
+        transform = { rotate = [0, 45, 0], scale = [2, 1, 1] }
+
+        [[objects]]
+        type = "sphere"
+        center = [0, 3, 0]
+        radius = 1
+        material = "lamp"
+        transform_end = { translate = [1, 0, 0] }
+
+        [[objects]]
+        type = "triangle"
+        a = [0, 0, 0]
+        b = [1, 0, 0]
+        c = [0, 1, 0]
+        material = "lamp"
+
+        [[objects]]
+        type = "mesh"
+        vertices = [[0, 5, 0], [1, 5, 0], [1, 5, 1], [0, 5, 1]]
+        faces = [[0, 1, 2], [0, 2, 3]]
+        material = "lamp"
+
+        [[objects]]
+        type = "sphere"
+        center = [0, -100, 0]
+        radius = 99
+        material = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(scene.world.objects().len(), 5);
+    assert_eq!(scene.lights.objects().len(), 4);
+}
+
+#[test]
+fn errors_name_their_line() {
+    let (line, message) = error_line("[camera]\nvfov = 40\nimage_widht = 10\n");
+    assert_eq!(line, 3);
+    assert!(message.contains("image_widht"), "{message}");
+
+    let (line, message) = error_line("[camera]\n\nvfov = 4 0\nimage_width = 10\n");
+    assert_eq!(line, 3);
+    assert!(!message.is_empty());
+
+    let (line, _) =
+        error_line("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = \"big\"\n");
+    assert_eq!(line, 4);
+
+    let (line, message) = error_line("[[objects]]\ntype = \"teapot\"\n");
+    assert_eq!(line, 2);
+    assert!(
+        message.contains("unknown object type `teapot`"),
+        "{message}"
+    );
+}
+
+#[test]
+fn referenced_files_resolve_next_to_the_scene() {
+    let dir = std::env::temp_dir().join(format!("ray-tracing-{}-scene", std::process::id()));
+    fs::create_dir_all(dir.join("meshes")).unwrap();
+    fs::write(
+        dir.join("meshes/tri.obj"),
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("scene.toml"),
+        "[[objects]]\ntype = \"obj\"\npath = \"meshes/tri.obj\"\n\
+         material = { type = \"lambertian\", albedo = [0.5, 0.5, 0.5] }\n\
+         [[objects]]\ntype = \"obj\"\npath = \"missing.obj\"\n",
+    )
+    .unwrap();
+
+    let err = Scene::load(dir.join("scene.toml")).err().unwrap();
+    let message = err.to_string();
+    assert!(message.starts_with("line 7:"), "{message}");
+    assert!(message.contains("missing.obj"), "{message}");
+
+    let source = fs::read_to_string(dir.join("scene.toml")).unwrap();
+    let first = source.split("[[objects]]").nth(1).unwrap();
+    fs::write(dir.join("scene.toml"), format!("[[objects]]{first}")).unwrap();
+    let scene = Scene::load(dir.join("scene.toml")).unwrap();
+    assert!((scene.world.bounding_box().x.max - 1.0).abs() < 1e-3);
+
+    fs::remove_dir_all(dir).unwrap();
+}